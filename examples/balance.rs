@@ -0,0 +1,297 @@
+//! Plays thousands of headless games of a level with a computer player and prints statistics useful for balancing
+//! it.
+//!
+//! The level is built from the options as a ``Level`` limited by moves, and a game ends as soon as the level is won
+//! or lost.
+//!
+//! ```text
+//! cargo run --release --example balance -- --games 1000 --strategy greedy --skill 0.8 --score 12000 --moves 20
+//! cargo run --release --example balance -- --strategy objectives --collect 0:20 --collect 1:20 --moves 25
+//! cargo run --release --example balance -- --games 1000 --csv > games.csv
+//! ```
+//!
+//! Options:
+//! - `--games`: number of games to play, 1000 by default
+//! - `--seed`: seed of the first game, every next game uses the next seed, 0 by default
+//! - `--strategy`: `random`, `greedy`, `lookahead` or `objectives`, `greedy` by default
+//! - `--skill`: chance of the computer player picking its best move, 1.0 by default
+//! - `--moves`: move limit of the level, 20 by default
+//! - `--score`: adds an objective to reach the given score with the default ``ScoreRules``, 12000 if there are no
+//!   other objectives
+//! - `--collect`: adds an objective to pop the given number of gems of a type, as `type:count`, and can be repeated
+//! - `--gems`: number of gem types, 5 by default
+//! - `--width` and `--height`: board dimensions, 10 by 10 by default
+//! - `--csv`: prints one line per game as CSV, moving the summary to stderr
+use std::{collections::BTreeMap, io::Write};
+
+use bevy::{prelude::*, time::TimePlugin};
+use bevy_match3::prelude::*;
+
+struct Options {
+    games: u64,
+    seed: u64,
+    strategy: String,
+    skill: f32,
+    moves: u32,
+    objectives: Vec<Objective>,
+    config: Match3Config,
+    csv: bool,
+}
+
+impl Options {
+    fn parse() -> Self {
+        let mut options = Options {
+            games: 1000,
+            seed: 0,
+            strategy: "greedy".to_string(),
+            skill: 1.0,
+            moves: 20,
+            objectives: vec![],
+            config: Match3Config::default(),
+            csv: false,
+        };
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            if arg == "--csv" {
+                options.csv = true;
+                continue;
+            }
+            let value = args
+                .next()
+                .unwrap_or_else(|| panic!("Missing value for {arg}"));
+            match arg.as_str() {
+                "--games" => options.games = parse(&arg, &value),
+                "--seed" => options.seed = parse(&arg, &value),
+                "--strategy" => options.strategy = value.clone(),
+                "--skill" => options.skill = parse(&arg, &value),
+                "--moves" => options.moves = parse(&arg, &value),
+                "--score" => options
+                    .objectives
+                    .push(Objective::Score(parse(&arg, &value))),
+                "--collect" => {
+                    let (typ, count) = value
+                        .split_once(':')
+                        .unwrap_or_else(|| panic!("Invalid value {value} for {arg}"));
+                    options
+                        .objectives
+                        .push(Objective::Collect(parse(&arg, typ), parse(&arg, count)));
+                }
+                "--gems" => options.config.gem_types = parse(&arg, &value),
+                "--width" => options.config.board_dimensions.x = parse(&arg, &value),
+                "--height" => options.config.board_dimensions.y = parse(&arg, &value),
+                _ => panic!("Unknown option {arg}"),
+            }
+        }
+        if options.objectives.is_empty() {
+            options.objectives.push(Objective::Score(12000));
+        }
+        options
+    }
+
+    fn level(&self) -> Level {
+        self.objectives.iter().fold(
+            Level::new(LevelLimit::Moves(self.moves)),
+            |level, objective| level.with_objective(objective.clone()),
+        )
+    }
+
+    fn ai_player(&self, seed: u64) -> AiPlayer {
+        match self.strategy.as_str() {
+            "random" => AiPlayer::new(RandomMove, self.skill, seed),
+            "greedy" => AiPlayer::new(Greedy, self.skill, seed),
+            "lookahead" => AiPlayer::new(Lookahead { samples: 3 }, self.skill, seed),
+            "objectives" => AiPlayer::new(ObjectiveFocused { samples: 3 }, self.skill, seed),
+            strategy => panic!("Unknown strategy {strategy}"),
+        }
+    }
+}
+
+fn parse<T: std::str::FromStr>(arg: &str, value: &str) -> T {
+    value
+        .parse()
+        .unwrap_or_else(|_| panic!("Invalid value {value} for {arg}"))
+}
+
+/// What happened over a single game
+#[derive(Default)]
+struct Game {
+    seed: u64,
+    moves: u32,
+    cleared: usize,
+    score: u64,
+    won: bool,
+    /// The number of rounds of matches following each move
+    cascades: Vec<usize>,
+    deadlocks: u32,
+    shuffles: u32,
+    regenerations: u32,
+    /// Whether the game ended on a board with no moves left that was never fixed
+    stuck: bool,
+}
+
+fn main() {
+    let options = Options::parse();
+    let games = (options.seed..options.seed + options.games)
+        .map(|seed| play(&options, seed))
+        .collect::<Vec<_>>();
+    if options.csv {
+        print_csv(&games);
+        print_summary(&options, &games, &mut std::io::stderr());
+    } else {
+        print_summary(&options, &games, &mut std::io::stdout());
+    }
+}
+
+/// Plays a single game of the level by sending the computer player's moves to a board in an app without any
+/// rendering, until the level is won or lost
+fn play(options: &Options, seed: u64) -> Game {
+    let mut app = App::new();
+    app.add_plugins((TimePlugin, Match3Plugin));
+    let board = Board::new(&Match3Config {
+        seed: Some(seed),
+        ..options.config.clone()
+    })
+    .with_scoring(ScoreRules::default());
+    let entity = app
+        .world_mut()
+        .spawn((board, options.level(), options.ai_player(seed)))
+        .id();
+
+    let mut game = Game { seed, ..default() };
+    loop {
+        let world = app.world_mut();
+        let mut query = world.query::<(&Board, &Level, &mut AiPlayer, &mut BoardCommands)>();
+        let (board, level, mut ai, mut board_commands) = query.get_mut(world, entity).unwrap();
+        if level.state() != LevelState::Playing {
+            break;
+        }
+        let Some(command) = ai.command(board, level) else {
+            game.stuck = true;
+            break;
+        };
+        board_commands.push(command).unwrap();
+
+        // Keep popping matches until the board settles
+        let mut cascades = 0;
+        loop {
+            app.update();
+            let world = app.world_mut();
+            let mut query = world.query::<(&mut BoardEvents, &mut BoardCommands)>();
+            let (mut events, mut board_commands) = query.get_mut(world, entity).unwrap();
+            let mut settled = true;
+            while let Ok(event) = events.pop() {
+                match event {
+                    BoardEvent::Matched(matches) => {
+                        cascades += 1;
+                        settled = false;
+                        board_commands
+                            .push(BoardCommand::Pop(
+                                matches.without_duplicates().iter().copied().collect(),
+                            ))
+                            .unwrap();
+                    }
+                    BoardEvent::Popped(_) => game.cleared += 1,
+                    BoardEvent::NoMovesLeft => game.deadlocks += 1,
+                    BoardEvent::Shuffled(_) => game.shuffles += 1,
+                    BoardEvent::Regenerated(_) => game.regenerations += 1,
+                    _ => {}
+                }
+            }
+            if settled {
+                break;
+            }
+        }
+        game.cascades.push(cascades);
+    }
+    let level = app.world().get::<Level>(entity).unwrap();
+    game.moves = options.moves - level.moves_left().unwrap_or_default();
+    game.score = level.score();
+    game.won = level.state() == LevelState::Won;
+    game
+}
+
+fn print_csv(games: &[Game]) {
+    println!("seed,moves,cleared,score,won,longest_cascade,deadlocks,shuffles,regenerations,stuck");
+    for game in games {
+        println!(
+            "{},{},{},{},{},{},{},{},{},{}",
+            game.seed,
+            game.moves,
+            game.cleared,
+            game.score,
+            game.won,
+            game.cascades.iter().max().unwrap_or(&0),
+            game.deadlocks,
+            game.shuffles,
+            game.regenerations,
+            game.stuck,
+        );
+    }
+}
+
+fn print_summary(options: &Options, games: &[Game], out: &mut impl Write) {
+    let count = games.len().max(1) as f32;
+    let moves = games.iter().map(|game| game.moves).sum::<u32>().max(1) as f32;
+    let cleared = games.iter().map(|game| game.cleared).sum::<usize>() as f32;
+    let score = games.iter().map(|game| game.score).sum::<u64>() as f32;
+    let won = games.iter().filter(|game| game.won).collect::<Vec<_>>();
+    let deadlocks = games.iter().map(|game| game.deadlocks).sum::<u32>() as f32;
+    let shuffles = games.iter().map(|game| game.shuffles).sum::<u32>() as f32;
+    let regenerations = games.iter().map(|game| game.regenerations).sum::<u32>() as f32;
+    let mut histogram = BTreeMap::<usize, usize>::new();
+    for cascades in games.iter().flat_map(|game| game.cascades.iter()) {
+        *histogram.entry(*cascades).or_default() += 1;
+    }
+
+    writeln!(
+        out,
+        "{} games with the {} strategy at skill {}, meeting {:?} in {} moves",
+        games.len(),
+        options.strategy,
+        options.skill,
+        options.objectives,
+        options.moves
+    )
+    .unwrap();
+    writeln!(out, "Win rate: {:.1}%", won.len() as f32 / count * 100.0).unwrap();
+    if !won.is_empty() {
+        writeln!(
+            out,
+            "Average moves to win: {:.2}",
+            won.iter().map(|game| game.moves).sum::<u32>() as f32 / won.len() as f32
+        )
+        .unwrap();
+    }
+    writeln!(out, "Gems cleared per move: {:.2}", cleared / moves).unwrap();
+    writeln!(out, "Score per move: {:.2}", score / moves).unwrap();
+    writeln!(
+        out,
+        "Games running out of moves: {:.1}%",
+        games.iter().filter(|game| !game.won && !game.stuck).count() as f32 / count * 100.0
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "Games with deadlocks: {:.1}%",
+        games.iter().filter(|game| game.deadlocks > 0).count() as f32 / count * 100.0
+    )
+    .unwrap();
+    writeln!(out, "Deadlocks per move: {:.4}", deadlocks / moves).unwrap();
+    writeln!(out, "Shuffles per move: {:.4}", shuffles / moves).unwrap();
+    writeln!(out, "Regenerations per move: {:.4}", regenerations / moves).unwrap();
+    writeln!(
+        out,
+        "Games stuck without moves: {}",
+        games.iter().filter(|game| game.stuck).count()
+    )
+    .unwrap();
+    writeln!(out, "Cascade length histogram:").unwrap();
+    for (cascades, count) in histogram {
+        writeln!(
+            out,
+            "{cascades:>4} {count:>8} {:>6.2}%",
+            count as f32 / moves * 100.0
+        )
+        .unwrap();
+    }
+}
@@ -43,7 +43,9 @@ struct VisibleBoard(HashMap<UVec2, Entity>);
 #[derive(Component)]
 struct MainCamera;
 
-fn setup_graphics(mut commands: Commands, board: Res<Board>, asset_server: Res<AssetServer>) {
+fn setup_graphics(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let board = Board::new(&Match3Config::default());
+
     let board_side_length = GEM_SIDE_LENGTH * 10.0;
     let centered_offset_x = board_side_length / 2.0 - GEM_SIDE_LENGTH / 2.0;
     let centered_offset_y = board_side_length / 2.0 - GEM_SIDE_LENGTH / 2.0;
@@ -70,7 +72,7 @@ fn setup_graphics(mut commands: Commands, board: Res<Board>, asset_server: Res<A
         let child = commands
             .spawn((
                 Sprite {
-                    image: asset_server.load(&map_type_to_path(*typ)),
+                    image: asset_server.load(map_type_to_path(*typ)),
                     custom_size: Some(Vec2::new(GEM_SIDE_LENGTH, GEM_SIDE_LENGTH)),
                     ..Sprite::default()
                 },
@@ -83,9 +85,9 @@ fn setup_graphics(mut commands: Commands, board: Res<Board>, asset_server: Res<A
         commands.entity(vis_board).add_child(child);
     });
 
-    let board = VisibleBoard(gems);
-
-    commands.entity(vis_board).insert(board);
+    commands
+        .entity(vis_board)
+        .insert((board, VisibleBoard(gems)));
 }
 
 fn map_type_to_path(typ: u32) -> String {
@@ -115,18 +117,23 @@ fn move_to(
 
 fn consume_events(
     mut commands: Commands,
-    mut events: ResMut<BoardEvents>,
     mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
-    mut board_commands: ResMut<BoardCommands>,
     ass: Res<AssetServer>,
-    mut board: Query<(Entity, &mut VisibleBoard)>,
+    mut board: Query<(
+        Entity,
+        &mut VisibleBoard,
+        &mut BoardEvents,
+        &mut BoardCommands,
+    )>,
     animations: Query<(), With<MoveTo>>,
+    mut sprites: Query<&mut Sprite>,
 ) {
     if animations.iter().count() == 0 {
+        let Ok((board_entity, mut board, mut events, mut board_commands)) = board.single_mut()
+        else {
+            return;
+        };
         if let Ok(event) = events.pop() {
-            let Ok((board_entity, mut board)) = board.single_mut() else {
-                return;
-            };
             match event {
                 BoardEvent::Swapped(pos1, pos2) => {
                     let gem1 = board.0.get(&pos1).copied().unwrap();
@@ -148,7 +155,7 @@ fn consume_events(
                     commands.entity(gem).despawn();
                     spawn_explosion(
                         &ass,
-                        &mut *texture_atlases,
+                        &mut texture_atlases,
                         &mut commands,
                         &board_pos_to_world_pos(&pos),
                     );
@@ -182,7 +189,7 @@ fn consume_events(
                         let gem = commands
                             .spawn((
                                 Sprite {
-                                    image: ass.load(&map_type_to_path(typ)),
+                                    image: ass.load(map_type_to_path(typ)),
                                     custom_size: Some([50.0, 50.0].into()),
                                     ..Sprite::default()
                                 },
@@ -209,6 +216,23 @@ fn consume_events(
                     }
                     *board = temp_board;
                 }
+                BoardEvent::NoMovesLeft => {
+                    info!("No moves left, the board shuffles itself");
+                }
+                BoardEvent::Regenerated(gems) => {
+                    for (pos, typ) in gems {
+                        let gem = board.0.get(&pos).copied().unwrap();
+                        if let Ok(mut sprite) = sprites.get_mut(gem) {
+                            sprite.image = ass.load(map_type_to_path(typ));
+                        }
+                    }
+                }
+                BoardEvent::SpecialCreated(pos, special) => {
+                    let gem = board.0.get(&pos).copied().unwrap();
+                    if let Ok(mut sprite) = sprites.get_mut(gem) {
+                        sprite.color = special_color(special);
+                    }
+                }
                 _ => {
                     dbg!("Received unimplemented event", event);
                 }
@@ -217,6 +241,16 @@ fn consume_events(
     }
 }
 
+/// Tints special gems so they can be told apart from regular ones
+fn special_color(special: Special) -> Color {
+    match special {
+        Special::RowClearer => Color::srgb(1.0, 0.5, 0.5),
+        Special::ColumnClearer => Color::srgb(0.5, 0.5, 1.0),
+        Special::Bomb => Color::srgb(0.4, 0.4, 0.4),
+        Special::ColorBomb => Color::srgb(1.0, 1.0, 0.3),
+    }
+}
+
 fn board_pos_to_world_pos(pos: &UVec2) -> Vec2 {
     Vec2::new(
         pos.x as f32 * GEM_SIDE_LENGTH,
@@ -308,7 +342,7 @@ fn visualize_selection(
 }
 
 fn control(
-    mut board_commands: ResMut<BoardCommands>,
+    mut board_commands: Query<&mut BoardCommands>,
     mut selection: ResMut<Selection>,
     mut last_selection: Local<Selection>,
     transforms: Query<&Transform>,
@@ -324,6 +358,9 @@ fn control(
                     .xy()
                     / 50.0;
 
+                let Ok(mut board_commands) = board_commands.single_mut() else {
+                    return;
+                };
                 board_commands
                     .push(BoardCommand::Swap(
                         [selected_pos.x as u32, -selected_pos.y as u32].into(),
@@ -389,7 +426,7 @@ fn spawn_explosion(
 }
 
 fn shuffle(
-    mut board_commands: ResMut<BoardCommands>,
+    mut board_commands: Query<&mut BoardCommands>,
     mut key_event: MessageReader<KeyboardInput>,
     animations: Query<(), With<MoveTo>>,
 ) {
@@ -401,6 +438,9 @@ fn shuffle(
                 ..
             } = event
             {
+                let Ok(mut board_commands) = board_commands.single_mut() else {
+                    return;
+                };
                 board_commands.push(BoardCommand::Shuffle).unwrap();
             }
         }
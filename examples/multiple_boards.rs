@@ -0,0 +1,325 @@
+use bevy::window::PrimaryWindow;
+use bevy::{
+    input::{mouse::MouseButtonInput, ButtonState},
+    math::Vec3Swizzles,
+    platform::collections::{HashMap, HashSet},
+    prelude::*,
+};
+use bevy_match3::prelude::*;
+
+const GEM_SIDE_LENGTH: f32 = 50.0;
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(Window {
+                resizable: false,
+                title: "bevy_match3 multiple boards example".to_string(),
+                resolution: (1000, 500).into(),
+                ..default()
+            }),
+            ..default()
+        }))
+        .insert_resource(Selection::default())
+        .add_plugins(Match3Plugin)
+        .add_systems(Startup, setup_graphics)
+        .add_systems(
+            Update,
+            (move_to, consume_events, input, visualize_selection),
+        )
+        .run();
+}
+
+#[derive(Component, Clone)]
+struct VisibleBoard(HashMap<UVec2, Entity>);
+
+#[derive(Component)]
+struct MainCamera;
+
+fn setup_graphics(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        Camera2d,
+        Transform::from_xyz(475.0, -225.0, 1000.0),
+        MainCamera,
+    ));
+
+    // Two boards with different configs living side by side in the same world
+    let configs = [
+        (
+            Vec2::new(0.0, 0.0),
+            Match3Config {
+                gem_types: 5,
+                board_dimensions: [8, 8].into(),
+                ..default()
+            },
+        ),
+        (
+            Vec2::new(550.0, -50.0),
+            Match3Config {
+                gem_types: 4,
+                board_dimensions: [6, 6].into(),
+                ..default()
+            },
+        ),
+    ];
+
+    for (offset, config) in configs {
+        let board = Board::new(&config);
+
+        let vis_board = commands
+            .spawn((
+                Transform::from_translation(offset.extend(0.0)),
+                Visibility::default(),
+            ))
+            .id();
+
+        let mut gems = HashMap::default();
+        board.iter().for_each(|(position, typ)| {
+            let world_pos = board_pos_to_world_pos(position);
+            let child = commands
+                .spawn((
+                    Sprite {
+                        image: asset_server.load(map_type_to_path(*typ)),
+                        custom_size: Some(Vec2::new(GEM_SIDE_LENGTH, GEM_SIDE_LENGTH)),
+                        ..Sprite::default()
+                    },
+                    Transform::from_translation(world_pos.extend(0.0)),
+                    Visibility::default(),
+                ))
+                .id();
+            gems.insert(*position, child);
+            commands.entity(vis_board).add_child(child);
+        });
+
+        commands
+            .entity(vis_board)
+            .insert((board, VisibleBoard(gems)));
+    }
+}
+
+fn map_type_to_path(typ: u32) -> String {
+    format!("{typ}.png")
+}
+
+fn board_pos_to_world_pos(pos: &UVec2) -> Vec2 {
+    Vec2::new(
+        pos.x as f32 * GEM_SIDE_LENGTH,
+        -(pos.y as f32) * GEM_SIDE_LENGTH,
+    )
+}
+
+#[derive(Component)]
+struct MoveTo(Vec2);
+
+fn move_to(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut moves: Query<(Entity, &mut Transform, &MoveTo)>,
+) {
+    for (entity, mut transform, MoveTo(move_to)) in moves.iter_mut() {
+        if transform.translation == Vec3::new(move_to.x, move_to.y, transform.translation.z) {
+            commands.entity(entity).remove::<MoveTo>();
+        } else {
+            let mut movement = *move_to - transform.translation.xy();
+            movement = (movement.normalize() * time.delta_secs() * GEM_SIDE_LENGTH * 5.0)
+                .clamp_length_max(movement.length());
+            let movement = movement.extend(transform.translation.z);
+            transform.translation += movement;
+        }
+    }
+}
+
+fn consume_events(
+    mut commands: Commands,
+    ass: Res<AssetServer>,
+    mut boards: Query<(
+        Entity,
+        &mut VisibleBoard,
+        &mut BoardEvents,
+        &mut BoardCommands,
+    )>,
+    animations: Query<&ChildOf, With<MoveTo>>,
+    mut sprites: Query<&mut Sprite>,
+) {
+    // Each board only waits for its own animations, so one board never blocks the other
+    let animating = animations
+        .iter()
+        .map(ChildOf::parent)
+        .collect::<HashSet<_>>();
+
+    for (board_entity, mut board, mut events, mut board_commands) in boards.iter_mut() {
+        if animating.contains(&board_entity) {
+            continue;
+        }
+        let Ok(event) = events.pop() else {
+            continue;
+        };
+        match event {
+            BoardEvent::Swapped(pos1, pos2) => {
+                let gem1 = board.0.get(&pos1).copied().unwrap();
+                let gem2 = board.0.get(&pos2).copied().unwrap();
+                commands
+                    .entity(gem1)
+                    .insert(MoveTo(board_pos_to_world_pos(&pos2)));
+                commands
+                    .entity(gem2)
+                    .insert(MoveTo(board_pos_to_world_pos(&pos1)));
+                board.0.insert(pos2, gem1);
+                board.0.insert(pos1, gem2);
+            }
+            BoardEvent::Popped(pos) => {
+                let gem = board.0.remove(&pos).unwrap();
+                commands.entity(gem).despawn();
+            }
+            BoardEvent::Matched(matches) => {
+                board_commands
+                    .push(BoardCommand::Pop(
+                        matches.without_duplicates().iter().copied().collect(),
+                    ))
+                    .unwrap();
+            }
+            BoardEvent::Dropped(drops) => {
+                let mut new_board = board.clone();
+                for Drop { from, to } in drops {
+                    let gem = board.0.get(&from).copied().unwrap();
+                    new_board.0.insert(to, gem);
+                    new_board.0.remove(&from);
+                    commands
+                        .entity(gem)
+                        .insert(MoveTo(board_pos_to_world_pos(&to)));
+                }
+                *board = new_board;
+            }
+            BoardEvent::Spawned(spawns) => {
+                for (pos, typ) in spawns {
+                    let world_pos = board_pos_to_world_pos(&pos);
+                    let gem = commands
+                        .spawn((
+                            Sprite {
+                                image: ass.load(map_type_to_path(typ)),
+                                custom_size: Some(Vec2::new(GEM_SIDE_LENGTH, GEM_SIDE_LENGTH)),
+                                ..Sprite::default()
+                            },
+                            Transform::from_xyz(world_pos.x, GEM_SIDE_LENGTH * 2.0, 0.0),
+                            Visibility::default(),
+                        ))
+                        .insert(MoveTo(world_pos))
+                        .id();
+                    board.0.insert(pos, gem);
+                    commands.entity(board_entity).add_child(gem);
+                }
+            }
+            BoardEvent::Shuffled(moves) => {
+                let mut new_board = board.clone();
+                for (from, to) in moves {
+                    let gem = board.0.get(&from).copied().unwrap();
+                    commands
+                        .entity(gem)
+                        .insert(MoveTo(board_pos_to_world_pos(&to)));
+                    new_board.0.insert(to, gem);
+                }
+                *board = new_board;
+            }
+            BoardEvent::Regenerated(gems) => {
+                for (pos, typ) in gems {
+                    let gem = board.0.get(&pos).copied().unwrap();
+                    if let Ok(mut sprite) = sprites.get_mut(gem) {
+                        sprite.image = ass.load(map_type_to_path(typ));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The last clicked gem, remembered together with the board it belongs to
+#[derive(Default, Clone, Copy, Resource)]
+struct Selection(Option<(Entity, UVec2)>);
+
+fn input(
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut selection: ResMut<Selection>,
+    mut button_events: MessageReader<MouseButtonInput>,
+    camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut boards: Query<(Entity, &Board, &GlobalTransform, &mut BoardCommands)>,
+) {
+    for event in button_events.read() {
+        if let MouseButtonInput {
+            button: MouseButton::Left,
+            state: ButtonState::Pressed,
+            ..
+        } = event
+        {
+            let Ok(window) = window_query.single() else {
+                return;
+            };
+            let Ok((camera, camera_transform)) = camera.single() else {
+                return;
+            };
+            let Some(world_position) = window
+                .cursor_position()
+                .and_then(|cursor| camera.viewport_to_world(camera_transform, cursor).ok())
+                .map(|ray| ray.origin.truncate())
+            else {
+                return;
+            };
+
+            for (entity, board, transform, mut board_commands) in boards.iter_mut() {
+                let local = world_position - transform.translation().xy();
+                // round down to the gem coordinate
+                let x = ((local.x + GEM_SIDE_LENGTH / 2.0) / GEM_SIDE_LENGTH).floor();
+                let y = ((GEM_SIDE_LENGTH / 2.0 - local.y) / GEM_SIDE_LENGTH).floor();
+                if x < 0.0
+                    || y < 0.0
+                    || x >= board.dimensions().x as f32
+                    || y >= board.dimensions().y as f32
+                {
+                    continue;
+                }
+                let pos = UVec2::new(x as u32, y as u32);
+
+                selection.0 = match selection.0 {
+                    Some((selected_board, selected_pos)) if selected_board == entity => {
+                        board_commands
+                            .push(BoardCommand::Swap(selected_pos, pos))
+                            .unwrap();
+                        None
+                    }
+                    _ => Some((entity, pos)),
+                };
+            }
+        }
+    }
+}
+
+#[derive(Component)]
+struct SelectionRectangle;
+
+fn visualize_selection(
+    mut commands: Commands,
+    selection: Res<Selection>,
+    ass: Res<AssetServer>,
+    rectangle: Query<Entity, With<SelectionRectangle>>,
+) {
+    if selection.is_changed() {
+        for entity in rectangle.iter() {
+            commands.entity(entity).despawn();
+        }
+        if let Some((board, pos)) = selection.0 {
+            let rectangle = commands
+                .spawn((
+                    Sprite {
+                        image: ass.load("rectangle.png"),
+                        custom_size: Some(Vec2::new(GEM_SIDE_LENGTH, GEM_SIDE_LENGTH)),
+                        ..Sprite::default()
+                    },
+                    Transform::from_translation(board_pos_to_world_pos(&pos).extend(1.0)),
+                    Visibility::default(),
+                    SelectionRectangle,
+                ))
+                .id();
+            commands.entity(board).add_child(rectangle);
+        }
+    }
+}
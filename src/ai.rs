@@ -0,0 +1,279 @@
+use bevy::prelude::*;
+use rand::{prelude::SliceRandom, RngExt, SeedableRng};
+
+use crate::{board::*, level::Level, simulation::Simulation, systems::BoardCommand};
+
+/// Decides which moves a computer player prefers
+///
+/// # Examples
+///
+/// ```
+/// use bevy_match3::prelude::*;
+///
+/// /// Always plays the move closest to the bottom of the board
+/// struct Bottom;
+///
+/// impl Strategy for Bottom {
+///     fn rank(&self, board: &Board, _level: &Level, _seed: u64) -> Vec<BoardMove> {
+///         let mut moves = board.get_matching_moves().into_iter().collect::<Vec<_>>();
+///         moves.sort_by_key(|BoardMove(a, b)| std::cmp::Reverse((a.y.max(b.y), a.x.min(b.x))));
+///         moves
+///     }
+/// }
+///
+/// let ai = AiPlayer::new(Bottom, 1.0, 0);
+/// ```
+pub trait Strategy: Send + Sync {
+    /// Returns every matching move on the board, best first, for the given level played on it. The seed is fresh for
+    /// every decision and should be the only source of randomness, so computer players are reproducible
+    fn rank(&self, board: &Board, level: &Level, seed: u64) -> Vec<BoardMove>;
+}
+
+/// Plays a random matching move
+pub struct RandomMove;
+
+impl Strategy for RandomMove {
+    fn rank(&self, board: &Board, _level: &Level, seed: u64) -> Vec<BoardMove> {
+        let mut moves = board.get_matching_moves().into_iter().collect::<Vec<_>>();
+        // Into board order first, see ``BoardRng``
+        moves.sort_by_key(|BoardMove(a, b)| {
+            (a.y.min(b.y), a.x.min(b.x), a.y.max(b.y), a.x.max(b.x))
+        });
+        moves.shuffle(&mut BoardRng::seed_from_u64(seed));
+        moves
+    }
+}
+
+/// Plays the move clearing the most right away, as predicted by ``Board::hints``
+pub struct Greedy;
+
+impl Strategy for Greedy {
+    fn rank(&self, board: &Board, _level: &Level, _seed: u64) -> Vec<BoardMove> {
+        board
+            .hints()
+            .into_iter()
+            .map(|hint| hint.board_move)
+            .collect()
+    }
+}
+
+/// Plays the move clearing the most over the whole cascade following it, averaged over a number of simulations
+/// with different spawns
+pub struct Lookahead {
+    /// The number of simulations run for every move
+    pub samples: u64,
+}
+
+impl Strategy for Lookahead {
+    fn rank(&self, board: &Board, level: &Level, seed: u64) -> Vec<BoardMove> {
+        rank_simulated(board, level, seed, self.samples, |simulation| {
+            simulation.cleared + simulation.specials_created * 3
+        })
+    }
+}
+
+/// Plays the move making the most progress towards the objectives of the level over the whole cascade following it,
+/// preferring moves that clear the most when no move makes progress
+pub struct ObjectiveFocused {
+    /// The number of simulations run for every move
+    pub samples: u64,
+}
+
+impl Strategy for ObjectiveFocused {
+    fn rank(&self, board: &Board, level: &Level, seed: u64) -> Vec<BoardMove> {
+        // Progress is counted in thousandths of the target, so objectives of every scale weigh the same
+        let targets = level
+            .progress(board)
+            .iter()
+            .map(|progress| progress.target.max(1))
+            .collect::<Vec<_>>();
+        rank_simulated(board, level, seed, self.samples, |simulation| {
+            let progress = simulation
+                .objectives
+                .iter()
+                .zip(targets.iter())
+                .map(|(made, target)| made * 1000 / target)
+                .sum::<u64>();
+            progress as usize * 100 + simulation.cleared
+        })
+    }
+}
+
+/// Ranks the matching moves by the total value of their simulations, keeping the order of ``Board::hints`` for
+/// moves of the same value
+fn rank_simulated(
+    board: &Board,
+    level: &Level,
+    seed: u64,
+    samples: u64,
+    value: impl Fn(&Simulation) -> usize,
+) -> Vec<BoardMove> {
+    let mut moves = board
+        .hints()
+        .into_iter()
+        .map(|hint| {
+            let total = (0..samples.max(1))
+                .filter_map(|sample| {
+                    board.simulate(level, hint.board_move, seed.wrapping_add(sample))
+                })
+                .map(|simulation| value(&simulation))
+                .sum::<usize>();
+            (hint.board_move, total)
+        })
+        .collect::<Vec<_>>();
+    moves.sort_by(|(_, a), (_, b)| b.cmp(a));
+    moves
+        .into_iter()
+        .map(|(board_move, _)| board_move)
+        .collect()
+}
+
+/// A computer player picking moves for the board on the same entity
+///
+/// # Examples
+///
+/// ```
+/// use bevy::prelude::*;
+/// use bevy_match3::prelude::*;
+///
+/// fn play(mut boards: Query<(&Board, &Level, &mut AiPlayer, &mut BoardCommands)>) {
+///     for (board, level, mut ai, mut board_commands) in boards.iter_mut() {
+///         if let Some(command) = ai.command(board, level) {
+///             board_commands.push(command).unwrap();
+///         }
+///     }
+/// }
+///
+/// fn spawn_board(mut commands: Commands) {
+///     commands.spawn((
+///         Board::new(&Match3Config::default()),
+///         AiPlayer::new(Lookahead { samples: 3 }, 0.8, 42),
+///     ));
+/// }
+/// ```
+#[derive(Component)]
+pub struct AiPlayer {
+    strategy: Box<dyn Strategy>,
+    skill: f32,
+    rng: BoardRng,
+}
+
+impl AiPlayer {
+    /// Creates a new computer player following the given strategy.
+    ///
+    /// The skill from 0.0 to 1.0 is the chance of playing the best move of the strategy, otherwise a random
+    /// matching move is played. The seed makes the player's decisions reproducible
+    pub fn new(strategy: impl Strategy + 'static, skill: f32, seed: u64) -> Self {
+        Self {
+            strategy: Box::new(strategy),
+            skill: skill.clamp(0.0, 1.0),
+            rng: BoardRng::seed_from_u64(seed),
+        }
+    }
+
+    /// Returns the chance of the player picking the best move of its strategy
+    pub fn skill(&self) -> f32 {
+        self.skill
+    }
+
+    /// Replaces the chance of the player picking the best move of its strategy
+    pub fn set_skill(&mut self, skill: f32) {
+        self.skill = skill.clamp(0.0, 1.0);
+    }
+
+    /// Picks the next move to play on the given board and level, or ``None`` if there are no matching moves
+    pub fn pick(&mut self, board: &Board, level: &Level) -> Option<BoardMove> {
+        let seed = self.rng.random();
+        let moves = self.strategy.rank(board, level, seed);
+        if moves.is_empty() {
+            return None;
+        }
+        if self.rng.random::<f32>() < self.skill {
+            Some(moves[0])
+        } else {
+            Some(moves[self.rng.random_range(0..moves.len())])
+        }
+    }
+
+    /// Picks the next move to play on the given board and level as a swap command
+    pub fn command(&mut self, board: &Board, level: &Level) -> Option<BoardCommand> {
+        self.pick(board, level)
+            .map(|BoardMove(pos1, pos2)| BoardCommand::Swap(pos1, pos2))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ai::*, level::LevelLimit, objective::Objective, Match3Config};
+
+    fn board() -> Board {
+        Board::new(&Match3Config {
+            seed: Some(21),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn greedy_plays_best_hint() {
+        let board = board();
+        let mut ai = AiPlayer::new(Greedy, 1.0, 0);
+
+        assert_eq!(
+            ai.pick(&board, &Level::default()),
+            Some(board.hints()[0].board_move)
+        );
+    }
+
+    #[test]
+    fn strategies_play_matching_moves() {
+        let board = board();
+        let moves = board.get_matching_moves();
+        let mut players = [
+            AiPlayer::new(RandomMove, 1.0, 1),
+            AiPlayer::new(Greedy, 0.0, 2),
+            AiPlayer::new(Lookahead { samples: 2 }, 0.5, 3),
+            AiPlayer::new(ObjectiveFocused { samples: 1 }, 1.0, 4),
+        ];
+
+        for ai in players.iter_mut() {
+            assert!(moves.contains(&ai.pick(&board, &Level::default()).unwrap()));
+        }
+    }
+
+    #[test]
+    fn objective_focused_plays_for_objectives() {
+        #[rustfmt::skip]
+        let board: Board = vec![
+            vec![10, 11, 12, 13, 14],
+            vec![ 1,  1, 15,  1, 16],
+            vec![17, 18, 19, 20, 21],
+            vec![ 2,  2, 22,  2, 23],
+            vec![24, 25, 26, 27, 28],
+        ].into();
+        let level = Level::new(LevelLimit::Moves(5)).with_objective(Objective::Collect(2, 3));
+        let mut ai = AiPlayer::new(ObjectiveFocused { samples: 2 }, 1.0, 5);
+
+        // Both moves clear 3 gems, but only the row of 2s counts towards the objective
+        assert_eq!(
+            board.hints()[0].board_move,
+            BoardMove([2, 1].into(), [3, 1].into())
+        );
+        assert_eq!(
+            ai.pick(&board, &level),
+            Some(BoardMove([2, 3].into(), [3, 3].into()))
+        );
+    }
+
+    #[test]
+    fn reproducible_picks() {
+        let board = board();
+        let picks = [7, 7].map(|seed| {
+            let mut ai = AiPlayer::new(RandomMove, 0.5, seed);
+            (0..5)
+                .map(|_| ai.pick(&board, &Level::default()))
+                .collect::<Vec<_>>()
+        });
+
+        assert_eq!(picks[0], picks[1]);
+    }
+}
@@ -1,27 +1,194 @@
+use std::collections::BTreeSet;
+
 use bevy::{
     platform::collections::{HashMap, HashSet},
     prelude::*,
 };
-use rand::prelude::IteratorRandom;
+use rand::{rngs::Xoshiro256PlusPlus, RngExt, SeedableRng};
 
-use crate::mat::*;
+use crate::{
+    deadlock::*, jelly::*, level::*, mat::*, obstacle::*, score::*, shuffle::*, spawner::*,
+    special::*, systems::*, Match3Config,
+};
 
 /// The main struct representing the logical match 3 board
-#[derive(Eq, PartialEq, Debug, Clone, Resource)]
+///
+/// Each board lives on its own entity together with its own ``BoardCommands``, ``BoardEvents`` and ``Level``,
+/// so a world can host any number of independent boards.
+///
+/// # Examples
+///
+/// ```
+/// use bevy::prelude::*;
+/// use bevy_match3::prelude::*;
+///
+/// fn spawn_boards(mut commands: Commands) {
+///     commands.spawn(Board::new(&Match3Config::default()));
+///     commands.spawn(Board::new(&Match3Config {
+///         gem_types: 4,
+///         board_dimensions: [6, 6].into(),
+///         ..default()
+///     }));
+/// }
+/// ```
+#[derive(Eq, PartialEq, Debug, Clone, Component)]
+#[require(BoardCommands, BoardEvents, Level)]
 pub struct Board {
     pub(crate) dimensions: UVec2,
-    pub(crate) gems: HashMap<UVec2, u32>,
-    pub(crate) types: HashSet<u32>,
+    pub(crate) gems: HashMap<UVec2, Gem>,
+    pub(crate) types: BTreeSet<u32>,
+    pub(crate) weights: Vec<(u32, u32)>,
+    pub(crate) seed: u64,
+    pub(crate) rng: BoardRng,
+    pub(crate) last_swap: Option<(UVec2, UVec2)>,
+    pub(crate) match_rules: MatchRules,
+    pub(crate) voids: HashSet<UVec2>,
+    pub(crate) gap_rule: GapRule,
+    pub(crate) gravity: Gravity,
+    pub(crate) cell_gravity: HashMap<UVec2, Gravity>,
+    pub(crate) diagonal_slide: bool,
+    pub(crate) obstacles: HashMap<UVec2, Obstacle>,
+    pub(crate) spawners: HashMap<UVec2, Spawner>,
+    pub(crate) deadlock_policy: DeadlockPolicy,
+    pub(crate) shuffle_mode: ShuffleMode,
+    pub(crate) shuffle_specials: bool,
+    pub(crate) scoring: Option<ScoreRules>,
+    pub(crate) exits: HashSet<UVec2>,
+    pub(crate) jelly: HashMap<UVec2, Jelly>,
+}
+
+/// Decides how gems fall past void cells in a column
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default)]
+pub enum GapRule {
+    /// Gems fall straight past void cells into the playable cells below them
+    #[default]
+    FallThrough,
+    /// Void cells act as a floor, the gems above a void stay above it
+    Block,
+}
+
+/// The direction gems fall in when there is room for them
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default, Hash)]
+pub enum Gravity {
+    /// Gems fall toward higher y-values
+    #[default]
+    Down,
+    /// Gems fall toward lower y-values
+    Up,
+    /// Gems fall toward lower x-values
+    Left,
+    /// Gems fall toward higher x-values
+    Right,
+}
+
+impl Gravity {
+    /// Returns the position one step in this direction, if it is still within the given dimensions
+    fn step(&self, pos: &UVec2, dimensions: UVec2) -> Option<UVec2> {
+        let next = match self {
+            Gravity::Down => UVec2::new(pos.x, pos.y + 1),
+            Gravity::Up => UVec2::new(pos.x, pos.y.checked_sub(1)?),
+            Gravity::Left => UVec2::new(pos.x.checked_sub(1)?, pos.y),
+            Gravity::Right => UVec2::new(pos.x + 1, pos.y),
+        };
+        (next.x < dimensions.x && next.y < dimensions.y).then_some(next)
+    }
+
+    fn reverse(&self) -> Gravity {
+        match self {
+            Gravity::Down => Gravity::Up,
+            Gravity::Up => Gravity::Down,
+            Gravity::Left => Gravity::Right,
+            Gravity::Right => Gravity::Left,
+        }
+    }
+
+    /// Returns the two directions across this one, in the order gems slide in from them
+    fn sides(&self) -> [Gravity; 2] {
+        match self {
+            Gravity::Down | Gravity::Up => [Gravity::Left, Gravity::Right],
+            Gravity::Left | Gravity::Right => [Gravity::Up, Gravity::Down],
+        }
+    }
+
+    /// Returns how far along this direction the given position is, gems further along settle first
+    fn progress(&self, pos: &UVec2) -> i64 {
+        match self {
+            Gravity::Down => pos.y as i64,
+            Gravity::Up => -(pos.y as i64),
+            Gravity::Left => -(pos.x as i64),
+            Gravity::Right => pos.x as i64,
+        }
+    }
 }
 
+/// A single gem on the board
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct Gem {
+    /// The type of the gem, gems of the same type match with each other
+    pub typ: u32,
+    /// The kind of the gem, which decides how it behaves when matched or popped
+    pub kind: GemKind,
+}
+
+impl From<u32> for Gem {
+    fn from(typ: u32) -> Self {
+        Self {
+            typ,
+            kind: GemKind::Normal,
+        }
+    }
+}
+
+impl Gem {
+    /// Returns the special this gem carries, if any
+    pub fn special(&self) -> Option<Special> {
+        match self.kind {
+            GemKind::Normal | GemKind::Ingredient => None,
+            GemKind::Special(special) => Some(special),
+        }
+    }
+
+    /// Returns whether this gem takes part in matches with other gems of its type
+    pub fn is_matchable(&self) -> bool {
+        !matches!(
+            self.kind,
+            GemKind::Special(Special::ColorBomb) | GemKind::Ingredient
+        )
+    }
+}
+
+/// The different kinds of gems that can live on the board
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash, Default)]
+pub enum GemKind {
+    /// A regular gem that is simply removed when popped
+    #[default]
+    Normal,
+    /// A special gem that activates an effect when popped
+    Special(Special),
+    /// An item that never matches and is never popped, but falls and swaps like any other gem until it is
+    /// collected at an exit cell, see ``Board::with_exit``. Its type tells different ingredients apart
+    Ingredient,
+}
+
+/// The random number generator owned by each board. It is portable, so the same seed gives the same
+/// sequence of gems on every platform.
+///
+/// Gems and obstacles live in hash maps that iterate in a different order on every run, so anything collected
+/// from them is sorted into board order before a random pick, keeping the outcome down to the seed alone
+pub(crate) type BoardRng = Xoshiro256PlusPlus;
+
 impl std::fmt::Display for Board {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let res = (0..self.dimensions.y).map(|y| {
             f.write_fmt(format_args!(
-                "{:?}\n",
+                "[{}]\n",
                 (0..self.dimensions.x)
-                    .map(|x| self.gems[&<[u32; 2] as Into<UVec2>>::into([x, y])])
-                    .collect::<Vec<u32>>()
+                    .map(|x| match self.get(&[x, y].into()) {
+                        Some(typ) => typ.to_string(),
+                        None => "-".to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ")
             ))
         });
         for res in res {
@@ -36,117 +203,501 @@ impl std::fmt::Display for Board {
 
 impl From<Vec<Vec<u32>>> for Board {
     fn from(rows: Vec<Vec<u32>>) -> Self {
+        rows.into_iter()
+            .map(|row| row.into_iter().map(Some).collect::<Vec<_>>())
+            .collect::<Vec<_>>()
+            .into()
+    }
+}
+
+/// Creates a board where ``None`` marks a void cell that is not part of the board
+///
+/// # Examples
+///
+/// ```
+/// use bevy_match3::prelude::*;
+///
+/// let board: Board = vec![
+///     vec![None,    Some(0), None   ],
+///     vec![Some(1), Some(2), Some(3)],
+///     vec![None,    Some(4), None   ],
+/// ].into();
+///
+/// assert!(!board.is_cell(&[0, 0].into()));
+/// assert!(board.is_cell(&[1, 0].into()));
+/// ```
+impl From<Vec<Vec<Option<u32>>>> for Board {
+    fn from(rows: Vec<Vec<Option<u32>>>) -> Self {
         let mut gems = HashMap::default();
-        let mut width = 0;
-        let mut height = 0;
-        let mut types = HashSet::default();
+        let mut voids = HashSet::default();
+        let width = rows.iter().map(Vec::len).max().unwrap_or_default() as u32;
+        let height = rows.len() as u32;
+        let mut types = BTreeSet::default();
         rows.iter().enumerate().for_each(|(y, row)| {
-            height += 1;
-            row.iter().enumerate().for_each(|(x, gem)| {
-                gems.insert([x as u32, y as u32].into(), *gem);
-                types.insert(*gem);
-                if height == 1 {
-                    width += 1;
+            (0..width).for_each(|x| match row.get(x as usize).copied().flatten() {
+                Some(gem) => {
+                    gems.insert([x, y as u32].into(), gem.into());
+                    types.insert(gem);
+                }
+                None => {
+                    voids.insert([x, y as u32].into());
                 }
             })
         });
+        let seed = rand::rng().random();
         Board {
             gems,
             dimensions: [width, height].into(),
             types,
+            weights: vec![],
+            seed,
+            rng: BoardRng::seed_from_u64(seed),
+            last_swap: None,
+            match_rules: MatchRules::default(),
+            voids,
+            gap_rule: GapRule::default(),
+            gravity: Gravity::default(),
+            cell_gravity: HashMap::default(),
+            diagonal_slide: false,
+            obstacles: HashMap::default(),
+            spawners: HashMap::default(),
+            deadlock_policy: DeadlockPolicy::Nothing,
+            shuffle_mode: ShuffleMode::default(),
+            shuffle_specials: true,
+            scoring: None,
+            exits: HashSet::default(),
+            jelly: HashMap::default(),
         }
     }
 }
 
 impl Board {
+    /// Generates a new random board from the given config, guaranteed to contain no matches and at least one
+    /// matching move, unless the board is too small to ever have one
+    ///
+    /// Boards generated from configs with the same seed are identical
+    ///
+    /// # Panics
+    ///
+    /// Panics if the config has fewer than 3 gem types, or gem weights that list a gem type twice or give fewer
+    /// than 3 gem types a weight above 0
+    pub fn new(config: &Match3Config) -> Self {
+        let Match3Config {
+            board_dimensions,
+            gem_types,
+            ref gem_weights,
+            seed,
+            match_rules,
+            ref mask,
+            gap_rule,
+            gravity,
+            diagonal_slide,
+            deadlock_policy,
+            shuffle_mode,
+        } = *config;
+
+        if gem_types < 3 {
+            panic!("Cannot generate board with fewer than 3 different gem types");
+        }
+        if let Err(err) = validate_gem_weights(gem_weights) {
+            panic!("Cannot generate board with these gem weights: {err}");
+        }
+
+        let seed = seed.unwrap_or_else(|| rand::rng().random());
+
+        let voids = (0..board_dimensions.x)
+            .flat_map(|x| (0..board_dimensions.y).map(move |y| UVec2::new(x, y)))
+            .filter(|pos| mask.as_ref().is_some_and(|mask| !mask.contains(pos)))
+            .collect();
+
+        let mut board = Board {
+            dimensions: board_dimensions,
+            gems: HashMap::default(),
+            types: (0..gem_types).collect(),
+            weights: gem_weights.clone(),
+            seed,
+            rng: BoardRng::seed_from_u64(seed),
+            last_swap: None,
+            match_rules,
+            voids,
+            gap_rule,
+            gravity,
+            cell_gravity: HashMap::default(),
+            diagonal_slide,
+            obstacles: HashMap::default(),
+            spawners: HashMap::default(),
+            deadlock_policy,
+            shuffle_mode,
+            shuffle_specials: true,
+            scoring: None,
+            exits: HashSet::default(),
+            jelly: HashMap::default(),
+        };
+
+        board.fill();
+        board.clear_matches();
+        if board.get_matching_moves().is_empty() {
+            board.regenerate();
+        }
+
+        board
+    }
+
+    /// Replaces the rules deciding which arrangements of gems count as matches, keeping the gems as they are
+    pub fn with_match_rules(mut self, match_rules: MatchRules) -> Self {
+        self.match_rules = match_rules;
+        self
+    }
+
+    /// Replaces the rule deciding how gems fall past void cells
+    pub fn with_gap_rule(mut self, gap_rule: GapRule) -> Self {
+        self.gap_rule = gap_rule;
+        self
+    }
+
+    /// Replaces the direction gems fall in, cells with their own direction keep it
+    pub fn with_gravity(mut self, gravity: Gravity) -> Self {
+        self.gravity = gravity;
+        self
+    }
+
+    /// Gives a single cell its own direction for gems to fall in, overriding the board's gravity.
+    ///
+    /// A gem falls in the direction of the cell it is currently in, but never into a cell falling straight back at it,
+    /// so a board can for example be split into two halves falling toward the center.
+    /// Directions leading gems around in a circle are not supported
+    pub fn with_cell_gravity(mut self, pos: UVec2, gravity: Gravity) -> Self {
+        self.cell_gravity.insert(pos, gravity);
+        self
+    }
+
+    /// Enables or disables gems sliding diagonally into cells that nothing can fall straight into
+    pub fn with_diagonal_slide(mut self, diagonal_slide: bool) -> Self {
+        self.diagonal_slide = diagonal_slide;
+        self
+    }
+
+    /// Replaces the gem types new gems are picked from together with their weights, gem types left out of the
+    /// table no longer spawn. An empty table picks uniformly from the board's gem types
+    ///
+    /// # Panics
+    ///
+    /// Panics if the table is not empty and lists a gem type twice or gives fewer than 3 gem types a weight above 0
+    pub fn with_gem_weights(mut self, weights: Vec<(u32, u32)>) -> Self {
+        if let Err(err) = validate_gem_weights(&weights) {
+            panic!("Cannot use these gem weights: {err}");
+        }
+        self.weights = weights;
+        self
+    }
+
+    /// Returns the gem types new gems are picked from together with their weights, empty if the board picks
+    /// uniformly from its gem types
+    pub fn gem_weights(&self) -> &[(u32, u32)] {
+        &self.weights
+    }
+
+    /// Returns the direction gems fall in at the given position
+    pub fn gravity(&self, pos: &UVec2) -> Gravity {
+        self.cell_gravity.get(pos).copied().unwrap_or(self.gravity)
+    }
+
+    /// Returns the rectangular dimensions of the board, void cells included
+    pub fn dimensions(&self) -> UVec2 {
+        self.dimensions
+    }
+
+    /// Returns whether the given position is a playable cell of the board, as opposed to a void or outside the board
+    pub fn is_cell(&self, pos: &UVec2) -> bool {
+        pos.x < self.dimensions.x && pos.y < self.dimensions.y && !self.voids.contains(pos)
+    }
+
+    /// Returns the seed the board's random number generator was last seeded with
+    ///
+    /// Storing this alongside the commands sent to the board is enough to reproduce everything that happened on it
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Re-seeds the board's random number generator, all following fills and shuffles are determined by the new seed
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = BoardRng::seed_from_u64(seed);
+    }
+
     /// Returns a reference to the gem type at the given position.
     pub fn get(&self, pos: &UVec2) -> Option<&u32> {
+        self.gems.get(pos).map(|gem| &gem.typ)
+    }
+
+    /// Returns a reference to the full gem at the given position, including its kind
+    pub fn gem(&self, pos: &UVec2) -> Option<&Gem> {
         self.gems.get(pos)
     }
 
     /// Returns an iterator over the kvps in the board
     pub fn iter(&self) -> impl Iterator<Item = (&UVec2, &u32)> {
-        self.gems.iter()
+        self.gems.iter().map(|(pos, gem)| (pos, &gem.typ))
     }
 
     pub(crate) fn remove(&mut self, pos: &UVec2) {
         self.gems.remove(pos);
     }
 
-    pub(crate) fn insert(&mut self, pos: UVec2, typ: u32) {
-        self.gems.insert(pos, typ);
+    /// Returns the type of the gem at the given position if it can take part in a match
+    fn matchable_type(&self, pos: &UVec2) -> Option<u32> {
+        self.gem(pos)
+            .filter(|gem| gem.is_matchable())
+            .map(|gem| gem.typ)
     }
 
-    pub(crate) fn drop(&mut self) -> HashSet<(UVec2, UVec2)> {
-        let mut moves = HashSet::default();
-        for x in 0..self.dimensions.x {
-            for y in (0..self.dimensions.y).rev() {
-                if self.get(&[x, y].into()).is_none() {
-                    let mut offset = 0;
-                    for above in (0..y).rev() {
-                        if let Some(typ) = self.get(&[x, above].into()).cloned() {
-                            let new_pos = [x, y - offset];
-                            moves.insert(([x, above].into(), new_pos.into()));
-                            self.remove(&[x, above].into());
-                            self.insert(new_pos.into(), typ);
-                            offset += 1;
-                        }
-                    }
+    /// The most passes of falling or sliding a single drop takes. Every pass moves at least one gem further along,
+    /// so the limit only stops gems circling forever
+    fn max_passes(&self) -> u32 {
+        self.dimensions.x * self.dimensions.y
+    }
+
+    /// Lets every gem fall along the gravity of its cell until nothing can move anymore, returning the moves
+    /// ordered so that each gem only moves into a cell that is empty by the time it moves
+    pub(crate) fn drop(&mut self) -> Vec<(UVec2, UVec2)> {
+        // Where each moved gem started out, keyed by where it is now
+        let mut origins = HashMap::<UVec2, UVec2>::default();
+        self.fall(&mut origins);
+        if self.diagonal_slide {
+            for _ in 0..self.max_passes() {
+                if !self.slide(&mut origins) {
+                    break;
                 }
+                self.fall(&mut origins);
+            }
+        }
+
+        let mut pending = origins
+            .into_iter()
+            .map(|(to, from)| (from, to))
+            .filter(|(from, to)| from != to)
+            .collect::<Vec<_>>();
+        pending.sort_by_key(|(from, _)| (from.y, from.x));
+        let mut moves = Vec::with_capacity(pending.len());
+        while !pending.is_empty() {
+            let (ready, rest): (Vec<_>, Vec<_>) = pending
+                .iter()
+                .partition(|(_, to)| !pending.iter().any(|(from, _)| from == to));
+            if ready.is_empty() {
+                moves.extend(rest);
+                break;
             }
+            moves.extend(ready);
+            pending = rest;
         }
         moves
     }
 
+    /// Lets every gem fall straight along the gravity of its cell until nothing can move anymore
+    fn fall(&mut self, origins: &mut HashMap<UVec2, UVec2>) {
+        for _ in 0..self.max_passes() {
+            let mut positions = self.gems.keys().copied().collect::<Vec<_>>();
+            positions.sort_by_key(|pos| (-self.gravity(pos).progress(pos), pos.y, pos.x));
+            let mut moved = false;
+            for from in positions {
+                // The gem that started the pass here may have fallen on already
+                if self.gem(&from).is_none() {
+                    continue;
+                }
+                let mut to = from;
+                let mut visited = HashSet::<UVec2>::default();
+                while let Some(next) = self.fall_target(&to) {
+                    if !visited.insert(next) {
+                        break;
+                    }
+                    to = next;
+                }
+                if to != from {
+                    moved = true;
+                    self.move_gem(from, to, origins);
+                }
+            }
+            if !moved {
+                break;
+            }
+        }
+    }
+
+    /// Slides a single gem diagonally into the deepest empty cell that can't be reached by falling straight,
+    /// returning whether a gem has moved
+    fn slide(&mut self, origins: &mut HashMap<UVec2, UVec2>) -> bool {
+        let mut empty = (0..self.dimensions.x)
+            .flat_map(|x| (0..self.dimensions.y).map(move |y| UVec2::new(x, y)))
+            .filter(|pos| self.is_cell(pos) && !self.is_blocked(pos) && self.gem(pos).is_none())
+            .filter(|pos| self.is_sheltered(pos))
+            .collect::<Vec<_>>();
+        empty.sort_by_key(|pos| (-self.gravity(pos).progress(pos), pos.y, pos.x));
+        for to in empty {
+            let gravity = self.gravity(&to);
+            let Some(behind) = gravity.reverse().step(&to, self.dimensions) else {
+                continue;
+            };
+            let from = gravity
+                .sides()
+                .into_iter()
+                .filter_map(|side| side.step(&behind, self.dimensions))
+                .find(|from| self.gem(from).is_some() && !self.is_locked(from));
+            if let Some(from) = from {
+                self.move_gem(from, to, origins);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Returns whether nothing can fall straight into the given cell, because the way back against gravity is
+    /// closed off by an obstacle, a chained gem or a blocking void before reaching the edge of the board
+    fn is_sheltered(&self, pos: &UVec2) -> bool {
+        let back = self.gravity(pos).reverse();
+        let mut current = *pos;
+        while let Some(next) = back.step(&current, self.dimensions) {
+            if self.is_cell(&next) {
+                if self.is_blocked(&next) || self.is_locked(&next) {
+                    return true;
+                }
+                if self.gem(&next).is_some() {
+                    return false;
+                }
+            } else if self.gap_rule == GapRule::Block {
+                return true;
+            }
+            current = next;
+        }
+        false
+    }
+
+    fn move_gem(&mut self, from: UVec2, to: UVec2, origins: &mut HashMap<UVec2, UVec2>) {
+        let gem = self.gems.remove(&from).unwrap();
+        self.gems.insert(to, gem);
+        let origin = origins.remove(&from).unwrap_or(from);
+        origins.insert(to, origin);
+    }
+
+    /// Returns the cell the gem at the given position falls into next, if there is room for it
+    fn fall_target(&self, pos: &UVec2) -> Option<UVec2> {
+        if self.is_locked(pos) {
+            return None;
+        }
+        let gravity = self.gravity(pos);
+        let mut next = gravity.step(pos, self.dimensions)?;
+        while !self.is_cell(&next) {
+            if self.gap_rule == GapRule::Block {
+                return None;
+            }
+            next = gravity.step(&next, self.dimensions)?;
+        }
+        // Cells falling straight back into each other meet like a floor
+        let opposed = self.gravity(&next).step(&next, self.dimensions) == Some(*pos);
+        (!opposed && !self.is_blocked(&next) && self.gem(&next).is_none()).then_some(next)
+    }
+
+    /// Spawns new gems in the empty spawner cells, or in every empty cell if the board has no spawners
     pub(crate) fn fill(&mut self) -> HashSet<(UVec2, u32)> {
         let mut drops = HashSet::default();
         for x in 0..self.dimensions.x {
             for y in 0..self.dimensions.y {
-                let pos = [x, y];
-                if self.get(&pos.into()).is_none() {
-                    let new_type = self.types.iter().choose(&mut rand::rng()).copied().unwrap();
-                    self.insert(pos.into(), new_type);
-                    drops.insert((pos.into(), new_type));
+                let pos = UVec2::new(x, y);
+                if self.is_cell(&pos)
+                    && !self.is_blocked(&pos)
+                    && self.get(&pos).is_none()
+                    && (self.spawners.is_empty() || self.spawners.contains_key(&pos))
+                {
+                    let gem = self.spawn_gem(&pos);
+                    self.gems.insert(pos, gem);
+                    drops.insert((pos, gem.typ));
                 }
             }
         }
         drops
     }
 
+    pub(crate) fn random_type(&mut self) -> u32 {
+        if let Some(typ) = pick_weighted(&mut self.rng, &self.weights) {
+            return typ;
+        }
+        let index = self.rng.random_range(0..self.types.len() as u32);
+        self.types.iter().nth(index as usize).copied().unwrap()
+    }
+
     pub(crate) fn swap(&mut self, pos1: &UVec2, pos2: &UVec2) -> Result<(), SwapError> {
-        let gem1 = self.get(pos1).copied().ok_or(SwapError::NoGem(*pos1))?;
-        let gem2 = self.get(pos2).copied().ok_or(SwapError::NoGem(*pos2))?;
+        self.check_unlocked(pos1, pos2)?;
+        let gem1 = self.gem(pos1).copied().ok_or(SwapError::NoGem(*pos1))?;
+        let gem2 = self.gem(pos2).copied().ok_or(SwapError::NoGem(*pos2))?;
         self.gems.insert(*pos1, gem2);
         self.gems.insert(*pos2, gem1);
-        if self.get_matches().is_empty() {
+        if !self.is_color_bomb_swap(pos1, pos2) && self.get_matches().is_empty() {
             self.gems.insert(*pos1, gem1);
             self.gems.insert(*pos2, gem2);
             Err(SwapError::NoMatches)
         } else {
+            self.last_swap = Some((*pos1, *pos2));
             Ok(())
         }
     }
 
     /// Like swap but doesn't permanently change the board, useful for match checking
     fn try_swap(&mut self, pos1: &UVec2, pos2: &UVec2) -> Result<(), SwapError> {
-        let gem1 = self.get(pos1).copied().ok_or(SwapError::NoGem(*pos1))?;
-        let gem2 = self.get(pos2).copied().ok_or(SwapError::NoGem(*pos2))?;
+        self.check_unlocked(pos1, pos2)?;
+        let gem1 = self.gem(pos1).copied().ok_or(SwapError::NoGem(*pos1))?;
+        let gem2 = self.gem(pos2).copied().ok_or(SwapError::NoGem(*pos2))?;
         self.gems.insert(*pos1, gem2);
         self.gems.insert(*pos2, gem1);
-        if self.get_matches().is_empty() {
-            self.gems.insert(*pos1, gem1);
-            self.gems.insert(*pos2, gem2);
+        let result = if !self.is_color_bomb_swap(pos1, pos2) && self.get_matches().is_empty() {
             Err(SwapError::NoMatches)
         } else {
-            self.gems.insert(*pos1, gem1);
-            self.gems.insert(*pos2, gem2);
             Ok(())
+        };
+        self.gems.insert(*pos1, gem1);
+        self.gems.insert(*pos2, gem2);
+        result
+    }
+
+    fn check_unlocked(&self, pos1: &UVec2, pos2: &UVec2) -> Result<(), SwapError> {
+        match [pos1, pos2].into_iter().find(|pos| self.is_locked(pos)) {
+            Some(pos) => Err(SwapError::Locked(*pos)),
+            None => Ok(()),
         }
     }
 
+    /// Swapping a color bomb with any other gem except an ingredient always succeeds, even without a match
+    fn is_color_bomb_swap(&self, pos1: &UVec2, pos2: &UVec2) -> bool {
+        let gems = [pos1, pos2].map(|pos| self.gem(pos));
+        gems.iter()
+            .any(|gem| gem.is_some_and(|gem| gem.special() == Some(Special::ColorBomb)))
+            && !gems
+                .iter()
+                .any(|gem| gem.is_some_and(|gem| gem.kind == GemKind::Ingredient))
+    }
+
     pub(crate) fn get_matches(&self) -> Matches {
-        let mut matches = self.straight_matches(MatchDirection::Horizontal);
-        matches.append(&mut self.straight_matches(MatchDirection::Vertical));
+        let mut matches = Matches::default();
+        if self.match_rules.straight {
+            matches.append(&mut self.straight_matches(MatchDirection::Horizontal));
+            matches.append(&mut self.straight_matches(MatchDirection::Vertical));
+            matches = matches.merge_intersecting();
+        }
+        if self.match_rules.square {
+            matches.append(&mut self.square_matches());
+        }
+        matches
+    }
+
+    fn square_matches(&self) -> Matches {
+        let mut matches = Matches::default();
+        for y in 0..self.dimensions.y.saturating_sub(1) {
+            for x in 0..self.dimensions.x.saturating_sub(1) {
+                let square = [[x, y], [x + 1, y], [x, y + 1], [x + 1, y + 1]].map(UVec2::from);
+                let typ = self.matchable_type(&square[0]);
+                if typ.is_some() && square.iter().all(|pos| self.matchable_type(pos) == typ) {
+                    matches.add(Match::Square(square.into_iter().collect()));
+                }
+            }
+        }
         matches
     }
 
@@ -174,17 +725,17 @@ impl Board {
                 ]
                 .into();
 
-                let current_type = *self.get(&pos).unwrap();
-                if current_match.is_empty() || previous_type.unwrap() == current_type {
-                    previous_type = Some(current_type);
+                // Gems that can't match, like color bombs, break up any run they're part of
+                let current_type = self.matchable_type(&pos);
+                if current_type.is_some() && previous_type == current_type {
                     current_match.push(pos);
-                } else if previous_type.unwrap() != current_type {
+                } else {
                     match current_match.len() {
                         0..=2 => {}
                         _ => matches.add(Match::Straight(current_match.iter().cloned().collect())),
                     }
-                    current_match = vec![pos];
-                    previous_type = Some(current_type);
+                    current_match = current_type.map(|_| vec![pos]).unwrap_or_default();
+                    previous_type = current_type;
                 }
             }
             match current_match.len() {
@@ -203,14 +754,8 @@ impl Board {
             if matches.is_empty() {
                 break;
             }
-            for mat in matches.matches.iter() {
-                match mat {
-                    Match::Straight(gems) => {
-                        for gem in gems {
-                            self.remove(gem);
-                        }
-                    }
-                }
+            for gem in matches.without_duplicates() {
+                self.remove(&gem);
             }
             self.drop();
             self.fill();
@@ -249,13 +794,66 @@ impl Board {
     }
 }
 
-pub(crate) enum SwapError {
+/// Why a table of gem weights was rejected, see ``Board::with_gem_weights``
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum GemWeightsError {
+    /// The gem type is listed more than once
+    DuplicateType(u32),
+    /// Fewer than 3 gem types have a weight above 0, which would leave the board without matches to make
+    TooFewTypes,
+}
+
+impl std::fmt::Display for GemWeightsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GemWeightsError::DuplicateType(typ) => {
+                write!(f, "gem type {typ} is listed more than once")
+            }
+            GemWeightsError::TooFewTypes => {
+                write!(f, "fewer than 3 different gem types have a weight above 0")
+            }
+        }
+    }
+}
+
+/// Checks a table of gem types with their weights, an empty table is always valid
+pub(crate) fn validate_gem_weights(weights: &[(u32, u32)]) -> Result<(), GemWeightsError> {
+    let mut types = HashSet::new();
+    if let Some((typ, _)) = weights.iter().find(|(typ, _)| !types.insert(*typ)) {
+        return Err(GemWeightsError::DuplicateType(*typ));
+    }
+    if !weights.is_empty() && weights.iter().filter(|(_, weight)| *weight > 0).count() < 3 {
+        return Err(GemWeightsError::TooFewTypes);
+    }
+    Ok(())
+}
+
+/// Why two gems failed to swap, see ``BoardEvent::FailedSwap``
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum SwapError {
+    /// There is no gem at the given position
     NoGem(UVec2),
+    /// The swap would not make any matches
     NoMatches,
+    /// The gem at the given position is held in place by an obstacle
+    Locked(UVec2),
+    /// The level on the board's entity is over or out of moves, see ``Level``
+    LevelOver,
+}
+
+impl std::fmt::Display for SwapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SwapError::NoGem(pos) => write!(f, "there is no gem at {pos}"),
+            SwapError::NoMatches => write!(f, "the swap makes no matches"),
+            SwapError::Locked(pos) => write!(f, "the gem at {pos} is locked"),
+            SwapError::LevelOver => write!(f, "the level takes no more swaps"),
+        }
+    }
 }
 
 /// Represents a swap between two gems, order of gems doesn't matter
-#[derive(Eq, Debug)]
+#[derive(Eq, Debug, Clone, Copy)]
 pub struct BoardMove(pub UVec2, pub UVec2);
 
 impl PartialEq for BoardMove {
@@ -283,12 +881,11 @@ impl core::hash::Hash for BoardMove {
     }
 }
 
-trait BoardPosition {
+pub(crate) trait BoardPosition {
     fn left(&self) -> Self;
     fn right(&self) -> Self;
     fn up(&self) -> Self;
     fn down(&self) -> Self;
-    fn cardinally_adjacent(&self, other: &Self) -> bool;
 }
 
 impl BoardPosition for UVec2 {
@@ -307,20 +904,19 @@ impl BoardPosition for UVec2 {
     fn down(&self) -> Self {
         Self::new(self.x, self.y.saturating_add(1))
     }
-
-    fn cardinally_adjacent(&self, other: &Self) -> bool {
-        self == &other.left()
-            || self == &other.right()
-            || self == &other.up()
-            || self == &other.down()
-    }
 }
 
 #[cfg(test)]
 mod tests {
-    use bevy::math::UVec2;
+    use bevy::{math::UVec2, platform::collections::HashSet};
 
-    use crate::{mat::Matches, prelude::BoardMove, Board};
+    use super::{validate_gem_weights, GapRule, GemWeightsError, Gravity};
+    use crate::{
+        mat::{Match, MatchRules, Matches},
+        obstacle::{Obstacle, ObstacleKind},
+        prelude::{Board, BoardMove},
+        Match3Config,
+    };
 
     impl Matches {
         fn len(&self) -> usize {
@@ -350,6 +946,80 @@ mod tests {
         assert_eq!(*board.get(&[4, 6].into()).unwrap(), 34);
     }
 
+    #[test]
+    fn seeded_board_creation() {
+        let config = Match3Config {
+            seed: Some(1234),
+            ..Default::default()
+        };
+
+        let board = Board::new(&config);
+
+        assert_eq!(board.seed(), 1234);
+        assert_eq!(board, Board::new(&config));
+        assert_ne!(
+            board,
+            Board::new(&Match3Config {
+                seed: Some(4321),
+                ..config
+            })
+        );
+    }
+
+    #[test]
+    fn generate_with_moves() {
+        for seed in 0..20 {
+            let board = Board::new(&Match3Config {
+                gem_types: 6,
+                board_dimensions: [4, 4].into(),
+                seed: Some(seed),
+                ..Default::default()
+            });
+
+            assert!(board.get_matches().is_empty());
+            assert!(!board.get_matching_moves().is_empty());
+        }
+    }
+
+    #[test]
+    fn weighted_board_creation() {
+        let board = Board::new(&Match3Config {
+            gem_types: 5,
+            gem_weights: vec![(0, 0), (1, 1), (2, 1), (3, 1), (4, 4)],
+            seed: Some(1234),
+            ..Default::default()
+        });
+
+        assert!(board.iter().all(|(_, typ)| *typ != 0));
+        let common = board.iter().filter(|(_, typ)| **typ == 4).count();
+        let rare = board.iter().filter(|(_, typ)| **typ == 1).count();
+        assert!(common > rare);
+    }
+
+    #[test]
+    fn validate_weights() {
+        assert_eq!(validate_gem_weights(&[]), Ok(()));
+        assert_eq!(validate_gem_weights(&[(0, 1), (1, 1), (2, 1)]), Ok(()));
+        assert_eq!(
+            validate_gem_weights(&[(0, 1), (1, 1), (2, 0)]),
+            Err(GemWeightsError::TooFewTypes)
+        );
+        assert_eq!(
+            validate_gem_weights(&[(0, 1), (1, 1), (0, 1), (2, 1)]),
+            Err(GemWeightsError::DuplicateType(0))
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn short_gem_weights() {
+        Board::new(&Match3Config {
+            gem_types: 5,
+            gem_weights: vec![(0, 1), (1, 1)],
+            ..Default::default()
+        });
+    }
+
     #[test]
     fn check_horizontal_matches() {
         #[rustfmt::skip]
@@ -475,6 +1145,281 @@ mod tests {
         assert!(without_duplicates.contains::<UVec2>(&[4, 3].into()));
     }
 
+    #[test]
+    fn check_shaped_matches() {
+        #[rustfmt::skip]
+        let l_shape: Board = vec![
+            vec![ 5,  1,  2,  3],
+            vec![ 5,  6,  7,  8],
+            vec![ 5,  5,  5, 13],
+            vec![15, 16, 17, 18],
+        ].into();
+        #[rustfmt::skip]
+        let t_shape: Board = vec![
+            vec![ 0,  5,  2,  3],
+            vec![ 4,  5,  7,  8],
+            vec![ 5,  5,  5, 13],
+            vec![15, 16, 17, 18],
+        ].into();
+        #[rustfmt::skip]
+        let cross: Board = vec![
+            vec![ 0,  1,  2,  3],
+            vec![ 4,  5,  7,  8],
+            vec![ 5,  5,  5, 13],
+            vec![15,  5, 17, 18],
+        ].into();
+
+        let matches = l_shape.get_matches();
+        assert_eq!(matches.len(), 1);
+        assert!(
+            matches!(&matches.matches[0], Match::LShape(gems, pivot) if gems.len() == 5 && *pivot == [0, 2].into())
+        );
+
+        let matches = t_shape.get_matches();
+        assert_eq!(matches.len(), 1);
+        assert!(
+            matches!(&matches.matches[0], Match::TShape(gems, pivot) if gems.len() == 5 && *pivot == [1, 2].into())
+        );
+
+        let matches = cross.get_matches();
+        assert_eq!(matches.len(), 1);
+        assert!(
+            matches!(&matches.matches[0], Match::Cross(gems, pivot) if gems.len() == 5 && *pivot == [1, 2].into())
+        );
+        assert_eq!(matches.matches[0].longest_line(), 3);
+    }
+
+    #[test]
+    fn check_square_matches() {
+        #[rustfmt::skip]
+        let board: Board = vec![
+            vec![ 0,  1,  2,  3],
+            vec![ 4,  5,  5,  8],
+            vec![ 9,  5,  5, 13],
+            vec![15, 16, 17, 18],
+        ].into();
+
+        assert!(board.get_matches().is_empty());
+
+        let board = board.with_match_rules(MatchRules {
+            square: true,
+            ..Default::default()
+        });
+        let matches = board.get_matches();
+
+        assert_eq!(matches.len(), 1);
+        assert!(matches!(&matches.matches[0], Match::Square(gems) if gems.len() == 4));
+    }
+
+    #[test]
+    fn generate_without_squares() {
+        let board = Board::new(&Match3Config {
+            gem_types: 3,
+            match_rules: MatchRules {
+                square: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+
+        assert!(board.get_matches().is_empty());
+    }
+
+    #[test]
+    fn check_square_moves() {
+        #[rustfmt::skip]
+        let board: Board = vec![
+            vec![ 0,  1,  2,  3],
+            vec![ 4,  5,  5,  8],
+            vec![ 9,  5, 12,  5],
+            vec![15, 16, 17, 18],
+        ].into();
+
+        let square_move = BoardMove(UVec2::new(2, 2), UVec2::new(3, 2));
+        assert!(!board.get_matching_moves().contains(&square_move));
+
+        let matching_moves = board
+            .with_match_rules(MatchRules {
+                square: true,
+                ..Default::default()
+            })
+            .get_matching_moves();
+
+        assert!(matching_moves.contains(&square_move));
+    }
+
+    #[test]
+    fn void_board_creation() {
+        #[rustfmt::skip]
+        let board: Board = vec![
+            vec![None,    Some(1), Some(2), None   ],
+            vec![Some(4), Some(5), Some(6), Some(7)],
+            vec![None,    Some(9), None],
+        ].into();
+
+        assert_eq!(board.dimensions, [4, 3].into());
+        assert!(!board.is_cell(&[0, 0].into()));
+        assert!(board.is_cell(&[1, 0].into()));
+        assert!(!board.is_cell(&[3, 2].into()));
+        assert!(!board.is_cell(&[4, 1].into()));
+        assert!(board.get(&[0, 0].into()).is_none());
+        assert_eq!(*board.get(&[3, 1].into()).unwrap(), 7);
+        assert_eq!(board.iter().count(), 7);
+        assert_eq!(
+            board.to_string(),
+            "[-, 1, 2, -]\n[4, 5, 6, 7]\n[-, 9, -, -]\n"
+        );
+    }
+
+    #[test]
+    fn matches_break_at_voids() {
+        #[rustfmt::skip]
+        let board: Board = vec![
+            vec![Some(0), Some(1), Some(2),  Some(3), Some(4)],
+            vec![Some(5), Some(5), None,     Some(5), Some(5)],
+            vec![Some(6), Some(7), Some(8),  Some(9), Some(10)],
+        ].into();
+
+        assert!(board.get_matches().is_empty());
+        assert!(board.get_matching_moves().is_empty());
+    }
+
+    #[test]
+    fn generate_with_mask() {
+        let mask = (0..6)
+            .flat_map(|x| (0..6).map(move |y| UVec2::new(x, y)))
+            .filter(|pos| (pos.x + pos.y) % 4 != 0)
+            .collect::<HashSet<_>>();
+        let board = Board::new(&Match3Config {
+            board_dimensions: [6, 6].into(),
+            mask: Some(mask.clone()),
+            ..Default::default()
+        });
+
+        assert_eq!(board.iter().count(), mask.len());
+        assert!(board.iter().all(|(pos, _)| mask.contains(pos)));
+        assert!(board.get_matches().is_empty());
+    }
+
+    #[test]
+    fn drop_past_void() {
+        #[rustfmt::skip]
+        let mut board: Board = vec![
+            vec![Some(0), Some(1)],
+            vec![Some(2), Some(3)],
+            vec![None,    Some(5)],
+            vec![Some(6), Some(7)],
+        ].into();
+
+        board.remove(&[0, 3].into());
+        let moves = board.drop();
+
+        assert_eq!(moves.len(), 2);
+        assert!(moves.contains(&([0, 1].into(), [0, 3].into())));
+        assert!(moves.contains(&([0, 0].into(), [0, 1].into())));
+        assert!(board.get(&[0, 2].into()).is_none());
+    }
+
+    #[test]
+    fn drop_blocked_by_void() {
+        #[rustfmt::skip]
+        let mut board: Board = vec![
+            vec![Some(0), Some(1)],
+            vec![Some(2), Some(3)],
+            vec![None,    Some(5)],
+            vec![Some(6), Some(7)],
+        ].into();
+        board = board.with_gap_rule(GapRule::Block);
+
+        board.remove(&[0, 3].into());
+        board.remove(&[0, 1].into());
+        let moves = board.drop();
+
+        assert_eq!(moves.len(), 1);
+        assert!(moves.contains(&([0, 0].into(), [0, 1].into())));
+        assert!(board.get(&[0, 3].into()).is_none());
+    }
+
+    #[test]
+    fn drop_sideways() {
+        #[rustfmt::skip]
+        let mut board: Board = vec![
+            vec![0, 1, 2],
+            vec![3, 4, 5],
+        ].into();
+        board = board.with_gravity(Gravity::Left);
+
+        board.remove(&[0, 0].into());
+        let moves = board.drop();
+
+        assert_eq!(
+            moves,
+            vec![
+                ([1, 0].into(), [0, 0].into()),
+                ([2, 0].into(), [1, 0].into())
+            ]
+        );
+        assert!(board.get(&[2, 0].into()).is_none());
+    }
+
+    #[test]
+    fn drop_toward_center() {
+        #[rustfmt::skip]
+        let mut board: Board = vec![
+            vec![0, 1, 2, 3],
+            vec![4, 5, 6, 7],
+        ].into();
+        for y in 0..2 {
+            board = board
+                .with_cell_gravity([0, y].into(), Gravity::Right)
+                .with_cell_gravity([1, y].into(), Gravity::Right)
+                .with_cell_gravity([2, y].into(), Gravity::Left)
+                .with_cell_gravity([3, y].into(), Gravity::Left);
+        }
+
+        board.remove(&[1, 1].into());
+        board.remove(&[2, 1].into());
+        let moves = board.drop();
+
+        assert_eq!(moves.len(), 2);
+        assert!(moves.contains(&([0, 1].into(), [1, 1].into())));
+        assert!(moves.contains(&([3, 1].into(), [2, 1].into())));
+        assert!(board.get(&[0, 1].into()).is_none());
+        assert!(board.get(&[3, 1].into()).is_none());
+        assert_eq!(*board.get(&[1, 0].into()).unwrap(), 1);
+    }
+
+    #[test]
+    fn slide_under_obstacle() {
+        #[rustfmt::skip]
+        let mut board: Board = vec![
+            vec![0, 1, 2],
+            vec![3, 4, 5],
+            vec![6, 7, 8],
+        ].into();
+        board = board.with_obstacle([1, 0].into(), Obstacle::new(ObstacleKind::Crate, 1));
+        board.remove(&[1, 1].into());
+        board.remove(&[1, 2].into());
+
+        assert!(board.clone().drop().is_empty());
+
+        board = board.with_diagonal_slide(true);
+        let moves = board.drop();
+
+        assert_eq!(
+            moves,
+            vec![
+                ([2, 0].into(), [1, 1].into()),
+                ([0, 1].into(), [1, 2].into()),
+                ([0, 0].into(), [0, 1].into()),
+            ]
+        );
+        assert_eq!(*board.get(&[1, 2].into()).unwrap(), 3);
+        assert_eq!(*board.get(&[1, 1].into()).unwrap(), 2);
+        assert!(board.get(&[0, 0].into()).is_none());
+        assert!(board.get(&[2, 0].into()).is_none());
+    }
+
     #[test]
     fn pop_gem() {
         #[rustfmt::skip]
@@ -0,0 +1,168 @@
+use bevy::math::UVec2;
+
+use crate::board::*;
+
+/// The number of times a board is regenerated before giving up on finding a matching move
+const REGENERATE_ATTEMPTS: usize = 100;
+
+/// Decides what a board does when it settles with no matches and no matching moves left
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default)]
+pub enum DeadlockPolicy {
+    /// Leaves the board as it is. This is the policy of boards created from rows of gems
+    Nothing,
+    /// Shuffles the board into one with no matches and at least one matching move, sending a
+    /// ``BoardEvent::Shuffled`` event. Falls back to regenerating if no such shuffle is found
+    #[default]
+    Shuffle,
+    /// Gives the gems new types in place until the board has a matching move, sending a
+    /// ``BoardEvent::Regenerated`` event. Boards too small or too locked up to ever have a matching move are left as
+    /// they are, so the ``BoardEvent::NoMovesLeft`` sent before is the last event
+    Regenerate,
+}
+
+impl Board {
+    /// Replaces what the board does when it settles with no matching moves left
+    pub fn with_deadlock_policy(mut self, deadlock_policy: DeadlockPolicy) -> Self {
+        self.deadlock_policy = deadlock_policy;
+        self
+    }
+
+    /// Returns what the board does when it settles with no matching moves left
+    pub fn deadlock_policy(&self) -> DeadlockPolicy {
+        self.deadlock_policy
+    }
+
+    /// Gives the regular gems new types in place until the board has no matches and at least one matching move,
+    /// returning the gems with their new types. Special and chained gems keep their types.
+    ///
+    /// Returns ``None`` and leaves the gems as they were if no such board is found
+    pub(crate) fn regenerate(&mut self) -> Option<Vec<(UVec2, u32)>> {
+        let mut positions = self
+            .gems
+            .iter()
+            .filter(|(pos, gem)| gem.kind == GemKind::Normal && !self.is_locked(pos))
+            .map(|(pos, _)| *pos)
+            .collect::<Vec<_>>();
+        positions.sort_by_key(|pos| (pos.x, pos.y));
+        let gems = self.gems.clone();
+
+        for _ in 0..REGENERATE_ATTEMPTS {
+            for pos in positions.iter() {
+                let typ = self.random_type();
+                self.gems.insert(*pos, typ.into());
+            }
+            // Re-rolling the gems in matches one at a time keeps the rest of the board as it is
+            for _ in 0..REGENERATE_ATTEMPTS {
+                let mut matched = self
+                    .get_matches()
+                    .without_duplicates()
+                    .into_iter()
+                    .filter(|pos| positions.contains(pos))
+                    .collect::<Vec<_>>();
+                if matched.is_empty() {
+                    break;
+                }
+                matched.sort_by_key(|pos| (pos.x, pos.y));
+                let typ = self.random_type();
+                self.gems.insert(matched[0], typ.into());
+            }
+            if self.get_matches().is_empty() && !self.get_matching_moves().is_empty() {
+                return Some(
+                    positions
+                        .into_iter()
+                        .map(|pos| (pos, self.gems[&pos].typ))
+                        .collect(),
+                );
+            }
+        }
+
+        self.gems = gems;
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{deadlock::*, level::Level, systems::*, test_util::events};
+
+    fn dead_board() -> Board {
+        #[rustfmt::skip]
+        let board: Board = vec![
+            vec![0, 1, 2, 0],
+            vec![1, 2, 0, 1],
+            vec![2, 0, 1, 2],
+            vec![0, 1, 2, 0],
+        ].into();
+        assert!(board.get_matching_moves().is_empty());
+        board
+    }
+
+    #[test]
+    fn shuffle_dead_board() {
+        let mut board = dead_board().with_deadlock_policy(DeadlockPolicy::Shuffle);
+
+        let events = events(&mut board, &mut Level::default(), BoardCommand::Pop(vec![]));
+
+        assert!(!board.get_matching_moves().is_empty());
+        assert!(matches!(
+            events[..],
+            [
+                BoardEvent::Dropped(_),
+                BoardEvent::Spawned(_),
+                BoardEvent::NoMovesLeft,
+                BoardEvent::Shuffled(_),
+                ..
+            ]
+        ));
+    }
+
+    #[test]
+    fn regenerate_dead_board() {
+        let mut board = dead_board().with_deadlock_policy(DeadlockPolicy::Regenerate);
+
+        let events = events(&mut board, &mut Level::default(), BoardCommand::Pop(vec![]));
+
+        assert!(!board.get_matching_moves().is_empty());
+        assert!(board.get_matches().is_empty());
+        let regenerated = events
+            .iter()
+            .find_map(|event| match event {
+                BoardEvent::Regenerated(gems) => Some(gems),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(regenerated.len(), 16);
+    }
+
+    #[test]
+    fn leave_dead_board() {
+        let mut board = dead_board();
+        let before = board.clone();
+
+        let events = events(&mut board, &mut Level::default(), BoardCommand::Pop(vec![]));
+
+        assert_eq!(board, before);
+        assert!(matches!(events.last(), Some(BoardEvent::NoMovesLeft)));
+    }
+
+    #[test]
+    fn fail_to_regenerate() {
+        // No arrangement of 3 gem types on a 2x2 board has a matching move without a match
+        #[rustfmt::skip]
+        let board: Board = vec![
+            vec![0, 1],
+            vec![2, 0],
+        ].into();
+        let mut board = board.with_deadlock_policy(DeadlockPolicy::Regenerate);
+        let before = board.clone();
+
+        let events = events(&mut board, &mut Level::default(), BoardCommand::Pop(vec![]));
+
+        assert!(board.get_matches().is_empty());
+        assert!(before.iter().all(|(pos, typ)| board.get(pos) == Some(typ)));
+        assert!(matches!(events.last(), Some(BoardEvent::NoMovesLeft)));
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, BoardEvent::Regenerated(_))));
+    }
+}
@@ -0,0 +1,175 @@
+use rand::RngExt;
+
+use crate::{board::*, level::Level, obstacle::*, systems::BoardEvent};
+
+impl Board {
+    /// Ends the turn of the given level once the board has settled. If no hazard was destroyed during the turn, one
+    /// hazard spreads into a random gem next to it with the same hit points, returning the event describing the spread
+    pub(crate) fn end_turn(&mut self, level: &mut Level) -> Option<BoardEvent> {
+        if !std::mem::take(&mut level.in_turn) || level.hazard_destroyed {
+            return None;
+        }
+        let mut candidates = self
+            .obstacles()
+            .filter(|(_, obstacle)| obstacle.kind == ObstacleKind::Hazard)
+            .flat_map(|(from, _)| {
+                [from.left(), from.right(), from.up(), from.down()]
+                    .into_iter()
+                    .map(|to| (*from, to))
+            })
+            .filter(|(_, to)| {
+                self.gem(to).is_some_and(|gem| gem.kind == GemKind::Normal)
+                    && self.obstacle(to).is_none()
+            })
+            .collect::<Vec<_>>();
+        if candidates.is_empty() {
+            return None;
+        }
+        // Into board order first, see ``BoardRng``
+        candidates.sort_by_key(|(from, to)| (to.y, to.x, from.y, from.x));
+        let (from, to) = candidates[self.rng.random_range(0..candidates.len())];
+        let hit_points = self.obstacles[&from].hit_points;
+        self.remove(&to);
+        self.obstacles
+            .insert(to, Obstacle::new(ObstacleKind::Hazard, hit_points));
+        Some(BoardEvent::HazardSpread(from, to))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::UVec2;
+
+    use crate::{
+        hazard::*,
+        level::*,
+        objective::*,
+        special::Special,
+        systems::*,
+        test_util::{cascade_board, play},
+    };
+
+    fn board(hazard: UVec2) -> Board {
+        let mut board = cascade_board();
+        board.reseed(0);
+        board.with_obstacle(hazard, Obstacle::new(ObstacleKind::Hazard, 1))
+    }
+
+    fn hazards(board: &Board) -> usize {
+        board
+            .obstacles()
+            .filter(|(_, obstacle)| obstacle.kind == ObstacleKind::Hazard)
+            .count()
+    }
+
+    #[test]
+    fn spread_after_turn() {
+        let mut board = board([4, 4].into());
+        let mut level = Level::default();
+
+        let events = play(
+            &mut board,
+            &mut level,
+            BoardCommand::Swap([2, 3].into(), [3, 3].into()),
+        );
+
+        let spread = events
+            .iter()
+            .find_map(|event| match event {
+                BoardEvent::HazardSpread(from, to) => Some((*from, *to)),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(spread.0, [4, 4].into());
+        assert!(spread.1 == [3, 4].into() || spread.1 == [4, 3].into());
+        assert!(board.get(&spread.1).is_none());
+        assert_eq!(hazards(&board), 2);
+        // Popping gems outside of a turn never spreads hazards
+        let events = play(
+            &mut board,
+            &mut level,
+            BoardCommand::Pop(vec![[0, 0].into()]),
+        );
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, BoardEvent::HazardSpread(_, _))));
+    }
+
+    #[test]
+    fn spread_sets_back_objectives() {
+        let mut board = board([4, 4].into());
+        let mut level = Level::new(LevelLimit::Moves(10))
+            .with_objective(Objective::ClearObstacles(ObstacleKind::Hazard));
+
+        let events = play(
+            &mut board,
+            &mut level,
+            BoardCommand::Swap([2, 3].into(), [3, 3].into()),
+        );
+
+        let spread = events
+            .iter()
+            .position(|event| matches!(event, BoardEvent::HazardSpread(_, _)))
+            .unwrap();
+        assert!(matches!(
+            events[spread + 1],
+            BoardEvent::ObjectiveProgress(
+                0,
+                Progress {
+                    current: 0,
+                    target: 2
+                }
+            )
+        ));
+    }
+
+    #[test]
+    fn no_spread_after_destroying_hazard() {
+        let mut board = board([3, 2].into())
+            .with_obstacle([0, 0].into(), Obstacle::new(ObstacleKind::Hazard, 1));
+        let mut level = Level::default();
+
+        let events = play(
+            &mut board,
+            &mut level,
+            BoardCommand::Swap([2, 3].into(), [3, 3].into()),
+        );
+
+        assert!(events.iter().any(|event| matches!(
+            event,
+            BoardEvent::ObstacleDestroyed(pos, ObstacleKind::Hazard) if *pos == [3, 2].into()
+        )));
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, BoardEvent::HazardSpread(_, _))));
+        assert_eq!(hazards(&board), 1);
+    }
+
+    #[test]
+    fn no_spread_without_candidates() {
+        // The hazard in the corner is walled in by a special gem and a gem under ice
+        let mut board =
+            board([4, 4].into()).with_obstacle([4, 3].into(), Obstacle::new(ObstacleKind::Ice, 1));
+        board.make_special(&[3, 4].into(), Special::Bomb);
+        let mut level = Level::default();
+
+        let events = play(
+            &mut board,
+            &mut level,
+            BoardCommand::Swap([2, 3].into(), [3, 3].into()),
+        );
+
+        assert!(events
+            .iter()
+            .any(|event| matches!(event, BoardEvent::Swapped(_, _))));
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, BoardEvent::HazardSpread(_, _))));
+        assert_eq!(hazards(&board), 1);
+        assert!(board.obstacle(&[4, 3].into()).is_some());
+        assert_eq!(
+            board.gem(&[3, 4].into()).and_then(Gem::special),
+            Some(Special::Bomb)
+        );
+    }
+}
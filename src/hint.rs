@@ -0,0 +1,166 @@
+use bevy::{math::UVec2, platform::collections::HashSet};
+
+use crate::{board::*, mat::Matches, special::Special};
+
+/// The score estimate of every gem cleared by a move
+const GEM_SCORE: u32 = 10;
+
+/// The predicted outcome of a single matching move, as returned by ``Board::hints``
+#[derive(Clone, Debug)]
+pub struct Hint {
+    /// The move this hint is about
+    pub board_move: BoardMove,
+    /// The matches the move would produce, empty for swaps with a color bomb
+    pub matches: Matches,
+    /// The number of gems the move would clear, including the gems cleared by activated specials but not the gems
+    /// turned into specials or the ingredients, which are never popped
+    pub cleared: usize,
+    /// The special gems the move would create
+    pub specials: Vec<(UVec2, Special)>,
+    /// A rough estimate of how good the move is, higher is better
+    pub score: u32,
+}
+
+/// Returns the score estimate of creating the given special
+fn special_score(special: Special) -> u32 {
+    match special {
+        Special::RowClearer | Special::ColumnClearer => 50,
+        Special::Bomb => 100,
+        Special::ColorBomb => 200,
+    }
+}
+
+impl Board {
+    /// Returns the predicted outcome of every matching move, best first.
+    ///
+    /// Only the first round of matches is predicted, the gems spawned afterwards are unknown until they spawn
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bevy_match3::prelude::*;
+    ///
+    /// let board = Board::new(&Match3Config::default());
+    /// if let Some(best) = board.hints().first() {
+    ///     let BoardMove(from, to) = best.board_move;
+    ///     println!("Try swapping {from} with {to} to clear {} gems", best.cleared);
+    /// }
+    /// ```
+    pub fn hints(&self) -> Vec<Hint> {
+        let mut moves = self.get_matching_moves().into_iter().collect::<Vec<_>>();
+        moves.sort_by_key(|BoardMove(a, b)| {
+            let (first, second) = if (a.y, a.x) < (b.y, b.x) {
+                (a, b)
+            } else {
+                (b, a)
+            };
+            (first.y, first.x, second.y, second.x)
+        });
+        let mut hints = moves
+            .into_iter()
+            .filter_map(|board_move| self.predict(board_move))
+            .collect::<Vec<_>>();
+        // Sorting is stable, so moves with the same outcome stay in board order
+        hints.sort_by(|a, b| b.score.cmp(&a.score).then(b.cleared.cmp(&a.cleared)));
+        hints
+    }
+
+    /// Predicts the outcome of the given move on a copy of the board
+    fn predict(&self, board_move: BoardMove) -> Option<Hint> {
+        let BoardMove(pos1, pos2) = board_move;
+        let mut board = self.clone();
+        board.swap(&pos1, &pos2).ok()?;
+
+        let (matches, mut popped) = match board.prime_color_bomb(&pos1, &pos2) {
+            Some(bomb) => (Matches::default(), vec![bomb]),
+            None => {
+                let matches = board.get_matches();
+                let mut popped = matches.without_duplicates().into_iter().collect::<Vec<_>>();
+                popped.sort_by_key(|pos| (pos.y, pos.x));
+                (matches, popped)
+            }
+        };
+        let specials = board.created_specials(&popped.iter().copied().collect());
+        let keep = specials.iter().map(|(pos, _)| *pos).collect::<HashSet<_>>();
+        board.activate_specials(&mut popped, &keep);
+        let cleared = popped
+            .iter()
+            .filter(|pos| {
+                !keep.contains(*pos)
+                    && board
+                        .gem(pos)
+                        .is_some_and(|gem| gem.kind != GemKind::Ingredient)
+                    && !board.is_locked(pos)
+            })
+            .count();
+
+        let score = cleared as u32 * GEM_SCORE
+            + specials
+                .iter()
+                .map(|(_, special)| special_score(*special))
+                .sum::<u32>();
+        Some(Hint {
+            board_move,
+            matches,
+            cleared,
+            specials,
+            score,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{board::*, special::Special};
+
+    #[test]
+    fn rank_hints() {
+        #[rustfmt::skip]
+        let board: Board = vec![
+            vec![ 0,  1,  2,  3,  4],
+            vec![ 5,  6,  7,  8,  9],
+            vec![10, 10, 12, 10, 14],
+            vec![15, 16, 10, 18, 19],
+            vec![20, 21, 22, 23, 24],
+            vec![25, 26, 27, 28, 29],
+            vec![30, 31, 32, 31, 31],
+        ].into();
+
+        let hints = board.hints();
+
+        assert_eq!(hints.len(), 3);
+        assert_eq!(hints[0].board_move, BoardMove([2, 2].into(), [2, 3].into()));
+        // One of the 4 matched gems stays behind as the special
+        assert_eq!(hints[0].cleared, 3);
+        assert_eq!(
+            hints[0].specials,
+            vec![([2, 2].into(), Special::RowClearer)]
+        );
+        // Moves with the same outcome are ordered by their position on the board
+        assert_eq!(hints[1].board_move, BoardMove([2, 2].into(), [3, 2].into()));
+        assert_eq!(hints[2].board_move, BoardMove([1, 6].into(), [2, 6].into()));
+        assert_eq!(hints[2].cleared, 3);
+        assert!(hints[2].specials.is_empty());
+        assert!(hints[0].score > hints[1].score);
+    }
+
+    #[test]
+    fn skip_ingredients() {
+        #[rustfmt::skip]
+        let board: Board = vec![
+            vec![ 0,  1,  2,  3,  4],
+            vec![ 5,  6,  7,  8,  9],
+            vec![10, 11, 12, 13, 14],
+            vec![30, 15, 16, 15, 19],
+            vec![20, 21, 15, 23, 24],
+        ].into();
+        let mut board = board.with_ingredient([1, 0].into(), 99);
+        board.make_special(&[1, 3].into(), Special::ColumnClearer);
+
+        let hints = board.hints();
+
+        assert_eq!(hints.len(), 1);
+        // The column clearer clears its column but the ingredient at the top of it
+        assert_eq!(hints[0].cleared, 6);
+    }
+}
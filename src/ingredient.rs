@@ -0,0 +1,156 @@
+use bevy::math::UVec2;
+
+use crate::board::*;
+
+impl Board {
+    /// Places an ingredient of the given type on the given position, replacing the gem there.
+    /// Ingredients never match and are only removed by reaching an exit cell
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bevy_match3::prelude::*;
+    ///
+    /// // Bring the ingredient at the top of column 4 down to the bottom row
+    /// let board = Board::new(&Match3Config::default())
+    ///     .with_ingredient([4, 0].into(), 0)
+    ///     .with_exit([4, 9].into());
+    /// ```
+    pub fn with_ingredient(mut self, pos: UVec2, typ: u32) -> Self {
+        self.gems.insert(
+            pos,
+            Gem {
+                typ,
+                kind: GemKind::Ingredient,
+            },
+        );
+        self
+    }
+
+    /// Turns the given position into an exit cell, collecting any ingredient that lands on it
+    pub fn with_exit(mut self, pos: UVec2) -> Self {
+        self.exits.insert(pos);
+        self
+    }
+
+    /// Returns whether the given position is an exit cell
+    pub fn is_exit(&self, pos: &UVec2) -> bool {
+        self.exits.contains(pos)
+    }
+
+    /// Removes every ingredient sitting on an exit cell, returning their positions and types
+    pub(crate) fn collect_ingredients(&mut self) -> Vec<(UVec2, u32)> {
+        let mut collected = self
+            .exits
+            .iter()
+            .filter_map(|pos| {
+                self.gem(pos)
+                    .filter(|gem| gem.kind == GemKind::Ingredient)
+                    .map(|gem| (*pos, gem.typ))
+            })
+            .collect::<Vec<_>>();
+        collected.sort_by_key(|(pos, _)| (pos.y, pos.x));
+        for (pos, _) in collected.iter() {
+            self.remove(pos);
+        }
+        collected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ingredient::*, level::Level, spawner::Spawner, special::Special, systems::*,
+        test_util::events,
+    };
+
+    #[test]
+    fn ingredients_never_match() {
+        #[rustfmt::skip]
+        let board: Board = vec![
+            vec![0, 1, 2],
+            vec![3, 4, 5],
+            vec![6, 7, 8],
+        ].into();
+        let board = board
+            .with_ingredient([0, 0].into(), 9)
+            .with_ingredient([1, 0].into(), 9)
+            .with_ingredient([2, 0].into(), 9);
+
+        assert!(board.get_matches().is_empty());
+    }
+
+    #[test]
+    fn collect_at_exit() {
+        #[rustfmt::skip]
+        let board: Board = vec![
+            vec![ 0,  1,  2],
+            vec![ 3,  4,  5],
+            vec![ 6,  7,  8],
+        ].into();
+        let mut board = board
+            .with_ingredient([1, 0].into(), 9)
+            .with_exit([1, 2].into());
+
+        let events = events(
+            &mut board,
+            &mut Level::default(),
+            BoardCommand::Pop(vec![[1, 1].into(), [1, 2].into()]),
+        );
+
+        assert!(events
+            .iter()
+            .any(|event| matches!(event, BoardEvent::Collected(pos, 9) if *pos == [1, 2].into())));
+        assert!(board
+            .iter()
+            .all(|(pos, _)| board.gem(pos).unwrap().kind != GemKind::Ingredient));
+        assert_eq!(board.iter().count(), 9);
+    }
+
+    #[test]
+    fn spawn_ingredients() {
+        #[rustfmt::skip]
+        let board: Board = vec![
+            vec![0, 1, 2],
+            vec![3, 4, 5],
+            vec![6, 7, 8],
+        ].into();
+        let mut board = board.with_spawner(
+            [0, 0].into(),
+            Spawner::new(vec![(0, 1)]).with_ingredients(vec![(9, 100)]),
+        );
+
+        events(
+            &mut board,
+            &mut Level::default(),
+            BoardCommand::Pop(vec![[0, 0].into()]),
+        );
+
+        let gem = board.gem(&[0, 0].into()).unwrap();
+        assert_eq!((gem.typ, gem.kind), (9, GemKind::Ingredient));
+    }
+
+    #[test]
+    fn color_bomb_ignores_ingredients() {
+        #[rustfmt::skip]
+        let board: Board = vec![
+            vec![0, 1, 0],
+            vec![2, 3, 4],
+            vec![0, 5, 0],
+        ].into();
+        let mut board = board.with_ingredient([1, 0].into(), 0);
+        board.make_special(&[1, 1].into(), Special::ColorBomb);
+
+        let events = events(
+            &mut board,
+            &mut Level::default(),
+            BoardCommand::Swap([1, 0].into(), [1, 1].into()),
+        );
+
+        assert!(matches!(
+            events[..],
+            [BoardEvent::FailedSwap(_, _, SwapError::NoMatches)]
+        ));
+        assert_eq!(board.iter().count(), 9);
+    }
+}
@@ -0,0 +1,179 @@
+use bevy::{math::UVec2, platform::collections::HashSet};
+
+use crate::board::*;
+
+/// A background tile under a gem, losing a layer whenever the gem on top of it is popped
+///
+/// # Examples
+///
+/// ```
+/// use bevy_match3::prelude::*;
+///
+/// let board = Board::new(&Match3Config::default())
+///     .with_jelly([0, 0].into(), Jelly::new(2))
+///     .with_jelly([9, 9].into(), Jelly::spreading(1));
+/// ```
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct Jelly {
+    /// The number of pops it takes to clear the jelly
+    pub layers: u32,
+    /// Whether popping a gem on this jelly spreads a layer of spreading jelly to the cells popped at the same time
+    /// that are connected to it through other popped cells, like the rest of its match
+    pub spreading: bool,
+}
+
+impl Jelly {
+    /// Creates a new jelly with the given number of layers
+    pub fn new(layers: u32) -> Self {
+        Self {
+            layers,
+            spreading: false,
+        }
+    }
+
+    /// Creates a new spreading jelly with the given number of layers
+    pub fn spreading(layers: u32) -> Self {
+        Self {
+            layers,
+            spreading: true,
+        }
+    }
+}
+
+impl Board {
+    /// Places jelly under the given position, replacing any jelly already there
+    pub fn with_jelly(mut self, pos: UVec2, jelly: Jelly) -> Self {
+        self.jelly.insert(pos, jelly);
+        self
+    }
+
+    /// Returns the jelly at the given position, if any
+    pub fn jelly(&self, pos: &UVec2) -> Option<&Jelly> {
+        self.jelly.get(pos)
+    }
+
+    /// Returns an iterator over all jelly on the board
+    pub fn jellies(&self) -> impl Iterator<Item = (&UVec2, &Jelly)> {
+        self.jelly.iter()
+    }
+
+    /// Takes a layer from the jelly under every popped position, and spreads a layer of spreading jelly to the
+    /// popped positions without any that are connected to popped spreading jelly through other popped positions.
+    /// Returns the changed positions with the layers they have left, where 0 means the jelly is gone
+    pub(crate) fn hit_jelly(&mut self, popped: &[UVec2]) -> Vec<(UVec2, u32)> {
+        let mut popped = popped.to_vec();
+        popped.sort_by_key(|pos| (pos.y, pos.x));
+        popped.dedup();
+        let cells = popped.iter().copied().collect::<HashSet<_>>();
+        let mut reached = popped
+            .iter()
+            .filter(|pos| self.jelly(pos).is_some_and(|jelly| jelly.spreading))
+            .copied()
+            .collect::<Vec<_>>();
+        let mut spread = reached.iter().copied().collect::<HashSet<_>>();
+        while let Some(pos) = reached.pop() {
+            for next in [pos.left(), pos.right(), pos.up(), pos.down()] {
+                if cells.contains(&next) && spread.insert(next) {
+                    reached.push(next);
+                }
+            }
+        }
+        let mut changes = vec![];
+        for pos in popped {
+            match self.jelly.get_mut(&pos) {
+                Some(jelly) => {
+                    jelly.layers = jelly.layers.saturating_sub(1);
+                    changes.push((pos, jelly.layers));
+                    if jelly.layers == 0 {
+                        self.jelly.remove(&pos);
+                    }
+                }
+                None if spread.contains(&pos) => {
+                    self.jelly.insert(pos, Jelly::spreading(1));
+                    changes.push((pos, 1));
+                }
+                None => {}
+            }
+        }
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{jelly::*, level::Level, systems::*, test_util::events};
+
+    fn jelly_events(board: &mut Board, command: BoardCommand) -> Vec<(UVec2, u32)> {
+        events(board, &mut Level::default(), command)
+            .into_iter()
+            .filter_map(|event| match event {
+                BoardEvent::JellyChanged(pos, layers) => Some((pos, layers)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn clear_jelly_layers() {
+        #[rustfmt::skip]
+        let board: Board = vec![
+            vec![0, 1, 2],
+            vec![3, 4, 5],
+            vec![6, 7, 8],
+        ].into();
+        let mut board = board
+            .with_jelly([0, 2].into(), Jelly::new(2))
+            .with_jelly([1, 2].into(), Jelly::new(1));
+
+        let events = jelly_events(
+            &mut board,
+            BoardCommand::Pop(vec![[0, 2].into(), [1, 2].into()]),
+        );
+
+        assert_eq!(events, vec![([0, 2].into(), 1), ([1, 2].into(), 0)]);
+        assert_eq!(board.jelly(&[0, 2].into()), Some(&Jelly::new(1)));
+        assert!(board.jelly(&[1, 2].into()).is_none());
+    }
+
+    #[test]
+    fn spread_jelly() {
+        #[rustfmt::skip]
+        let board: Board = vec![
+            vec![0, 1, 2],
+            vec![3, 4, 5],
+            vec![6, 7, 8],
+        ].into();
+        let mut board = board.with_jelly([0, 2].into(), Jelly::spreading(2));
+
+        let events = jelly_events(
+            &mut board,
+            BoardCommand::Pop(vec![[0, 2].into(), [1, 2].into(), [2, 2].into()]),
+        );
+
+        assert_eq!(
+            events,
+            vec![([0, 2].into(), 1), ([1, 2].into(), 1), ([2, 2].into(), 1)]
+        );
+        assert_eq!(board.jellies().count(), 3);
+        assert!(board.jellies().all(|(_, jelly)| jelly.spreading));
+    }
+
+    #[test]
+    fn spread_only_to_connected_pops() {
+        #[rustfmt::skip]
+        let board: Board = vec![
+            vec![0, 1, 2],
+            vec![3, 4, 5],
+            vec![6, 7, 8],
+        ].into();
+        let mut board = board.with_jelly([0, 0].into(), Jelly::spreading(2));
+
+        let events = jelly_events(
+            &mut board,
+            BoardCommand::Pop(vec![[0, 0].into(), [0, 1].into(), [2, 2].into()]),
+        );
+
+        assert_eq!(events, vec![([0, 0].into(), 1), ([0, 1].into(), 1)]);
+        assert!(board.jelly(&[2, 2].into()).is_none());
+    }
+}
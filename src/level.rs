@@ -0,0 +1,275 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{board::*, objective::Objective, systems::BoardEvent};
+
+/// The budget a level is played within
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum LevelLimit {
+    /// The number of successful swaps the player gets
+    Moves(u32),
+    /// The time the player gets, counted down by the ``Match3Plugin`` using Bevy's ``Time``
+    Time(Duration),
+    /// The player never runs out, so the level is only ever won by meeting its objectives
+    Endless,
+}
+
+/// Whether a level is still being played or how it ended
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default)]
+pub enum LevelState {
+    /// The level is being played
+    #[default]
+    Playing,
+    /// Every objective was met before the limit ran out
+    Won,
+    /// The limit ran out before every objective was met
+    Lost,
+}
+
+/// The level played on the board on the same entity, limiting the moves or time of the player and deciding when
+/// they win or lose. It also keeps the score and the state of the turn being played out.
+///
+/// Every board requires a level, boards spawned without one play an endless level without objectives
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use bevy::prelude::*;
+/// use bevy_match3::prelude::*;
+///
+/// fn spawn_boards(mut commands: Commands) {
+///     commands.spawn((
+///         Board::new(&Match3Config::default()).with_scoring(ScoreRules::default()),
+///         Level::new(LevelLimit::Moves(20)).with_objective(Objective::Score(5000)),
+///     ));
+///     commands.spawn((
+///         Board::new(&Match3Config::default()),
+///         Level::new(LevelLimit::Time(Duration::from_secs(60))),
+///     ));
+/// }
+/// ```
+#[derive(Eq, PartialEq, Debug, Clone, Component)]
+pub struct Level {
+    limit: LevelLimit,
+    pub(crate) objectives: Vec<Objective>,
+    /// What has been counted towards each objective so far
+    pub(crate) counts: Vec<u64>,
+    state: LevelState,
+    moves: u32,
+    elapsed: Duration,
+    pub(crate) score: u64,
+    /// The number of rounds of matches popped since the last swap
+    pub(crate) cascade: u32,
+    /// Whether a swap has started a turn that has not ended yet
+    pub(crate) in_turn: bool,
+    /// Whether a hazard was destroyed during the current turn
+    pub(crate) hazard_destroyed: bool,
+}
+
+impl Default for Level {
+    fn default() -> Self {
+        Self::new(LevelLimit::Endless)
+    }
+}
+
+impl Level {
+    /// Creates a new level with the given limit and no objectives.
+    ///
+    /// A level without objectives is won once the limit runs out, which suits score attack modes
+    pub fn new(limit: LevelLimit) -> Self {
+        Self {
+            limit,
+            objectives: vec![],
+            counts: vec![],
+            state: LevelState::Playing,
+            moves: 0,
+            elapsed: Duration::ZERO,
+            score: 0,
+            cascade: 0,
+            in_turn: false,
+            hazard_destroyed: false,
+        }
+    }
+
+    /// Adds an objective the player has to meet to win the level
+    pub fn with_objective(mut self, objective: Objective) -> Self {
+        self.objectives.push(objective);
+        self.counts.push(0);
+        self
+    }
+
+    /// Returns the budget the level is played within
+    pub fn limit(&self) -> LevelLimit {
+        self.limit
+    }
+
+    /// Returns the objectives the player has to meet to win the level
+    pub fn objectives(&self) -> &[Objective] {
+        &self.objectives
+    }
+
+    /// Returns whether the level is still being played or how it ended
+    pub fn state(&self) -> LevelState {
+        self.state
+    }
+
+    /// Returns the number of swaps left, or ``None`` if the level is not limited by moves
+    pub fn moves_left(&self) -> Option<u32> {
+        match self.limit {
+            LevelLimit::Moves(moves) => Some(moves.saturating_sub(self.moves)),
+            LevelLimit::Time(_) | LevelLimit::Endless => None,
+        }
+    }
+
+    /// Returns the time left, or ``None`` if the level is not limited by time
+    pub fn time_left(&self) -> Option<Duration> {
+        match self.limit {
+            LevelLimit::Time(time) => Some(time.saturating_sub(self.elapsed)),
+            LevelLimit::Moves(_) | LevelLimit::Endless => None,
+        }
+    }
+
+    /// Returns whether the player has run out of moves or time
+    fn is_exhausted(&self) -> bool {
+        self.moves_left() == Some(0) || self.time_left() == Some(Duration::ZERO)
+    }
+
+    /// Returns whether the level takes swaps, which is the case until it ends or its limit runs out
+    pub(crate) fn accepts_swaps(&self) -> bool {
+        self.state == LevelState::Playing && !self.is_exhausted()
+    }
+
+    /// Starts the turn of a successful swap, counting it against the move limit
+    pub(crate) fn start_turn(&mut self) {
+        self.moves += 1;
+        self.cascade = 0;
+        self.in_turn = true;
+        self.hazard_destroyed = false;
+    }
+
+    /// Counts down the time of a level being played with a time limit, returning whether the time just ran out
+    pub(crate) fn tick(&mut self, delta: Duration) -> bool {
+        if self.state != LevelState::Playing || self.time_left().is_none_or(|left| left.is_zero()) {
+            return false;
+        }
+        self.elapsed += delta;
+        self.time_left() == Some(Duration::ZERO)
+    }
+
+    /// Ends the level if every objective is met on the given board or the limit has run out, returning the event
+    /// announcing the outcome. Only called once the board has settled, so the last cascade still counts towards
+    /// the objectives
+    pub(crate) fn check(&mut self, board: &Board) -> Option<BoardEvent> {
+        if self.state != LevelState::Playing {
+            return None;
+        }
+        let met = self
+            .progress(board)
+            .iter()
+            .all(|progress| progress.is_done());
+        self.state = if met && (!self.objectives.is_empty() || self.is_exhausted()) {
+            LevelState::Won
+        } else if self.is_exhausted() {
+            LevelState::Lost
+        } else {
+            return None;
+        };
+        Some(match self.state {
+            LevelState::Won => BoardEvent::LevelWon,
+            _ => BoardEvent::LevelLost,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        level::*,
+        score::ScoreRules,
+        systems::*,
+        test_util::{cascade_board, play},
+    };
+
+    #[test]
+    fn lose_out_of_moves() {
+        let mut board = cascade_board().with_scoring(ScoreRules::default());
+        let mut level = Level::new(LevelLimit::Moves(1)).with_objective(Objective::Score(10_000));
+
+        let events = play(
+            &mut board,
+            &mut level,
+            BoardCommand::Swap([2, 3].into(), [3, 3].into()),
+        );
+
+        assert!(events
+            .iter()
+            .any(|event| matches!(event, BoardEvent::LevelLost)));
+        assert_eq!(level.moves_left(), Some(0));
+        assert_eq!(level.state(), LevelState::Lost);
+        assert!(!level.accepts_swaps());
+        let events = play(
+            &mut board,
+            &mut level,
+            BoardCommand::Swap([0, 0].into(), [1, 0].into()),
+        );
+        assert!(matches!(
+            events[..],
+            [BoardEvent::FailedSwap(_, _, SwapError::LevelOver)]
+        ));
+    }
+
+    #[test]
+    fn win_by_score() {
+        let mut level = Level::new(LevelLimit::Moves(5)).with_objective(Objective::Score(60));
+
+        let events = play(
+            &mut cascade_board().with_scoring(ScoreRules::default()),
+            &mut level,
+            BoardCommand::Swap([2, 3].into(), [3, 3].into()),
+        );
+
+        // The level only ends once the whole cascade has settled
+        let won = events
+            .iter()
+            .position(|event| matches!(event, BoardEvent::LevelWon))
+            .unwrap();
+        assert!(!events[won..]
+            .iter()
+            .any(|event| matches!(event, BoardEvent::Scored(_))));
+        assert_eq!(level.moves_left(), Some(4));
+        assert_eq!(level.state(), LevelState::Won);
+    }
+
+    #[test]
+    fn time_runs_out() {
+        let board = cascade_board().with_scoring(ScoreRules::default());
+        let mut level = Level::new(LevelLimit::Time(Duration::from_secs(60)));
+
+        assert!(!level.tick(Duration::from_secs(59)));
+        assert!(level.accepts_swaps());
+        assert!(level.tick(Duration::from_secs(2)));
+        assert!(!level.accepts_swaps());
+        assert!(matches!(level.check(&board), Some(BoardEvent::LevelWon)));
+        assert_eq!(level.time_left(), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn endless_by_default() {
+        let mut board = cascade_board().with_scoring(ScoreRules::default());
+        let mut level = Level::default();
+
+        play(
+            &mut board,
+            &mut level,
+            BoardCommand::Swap([2, 3].into(), [3, 3].into()),
+        );
+
+        assert_eq!(level.moves_left(), None);
+        assert_eq!(level.state(), LevelState::Playing);
+        assert!(level.accepts_swaps());
+        assert!(level.score() > 0);
+    }
+}
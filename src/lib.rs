@@ -5,93 +5,140 @@
 
 #![deny(missing_docs, clippy::doc_markdown)]
 
-use crate::systems::*;
-use bevy::{platform::collections::HashMap, prelude::*};
-use board::*;
-use rand::RngExt;
-
+use crate::{
+    board::{GapRule, Gravity},
+    deadlock::DeadlockPolicy,
+    mat::MatchRules,
+    shuffle::ShuffleMode,
+    systems::*,
+};
+use bevy::{platform::collections::HashSet, prelude::*};
+
+mod ai;
 mod board;
+mod deadlock;
+mod hazard;
+mod hint;
+mod ingredient;
+mod jelly;
+mod level;
 mod mat;
+mod objective;
+mod obstacle;
+mod score;
+mod shuffle;
+mod simulation;
+mod spawner;
+mod special;
 mod systems;
+#[cfg(test)]
+mod test_util;
 
 /// Use `bevy_match3::prelude::*;` to import common structs and plugins
 pub mod prelude {
+    pub use crate::ai::*;
     pub use crate::board::*;
+    pub use crate::deadlock::*;
+    pub use crate::hint::*;
+    pub use crate::jelly::*;
+    pub use crate::level::*;
     pub use crate::mat::*;
+    pub use crate::objective::*;
+    pub use crate::obstacle::*;
+    pub use crate::score::*;
+    pub use crate::shuffle::*;
+    pub use crate::simulation::*;
+    pub use crate::spawner::*;
+    pub use crate::special::*;
     pub use crate::systems::*;
     pub use crate::Match3Config;
     pub use crate::Match3Plugin;
 }
 
 /// The central logic plugin of the ``bevy_match3`` crate
+///
+/// The plugin only adds the systems driving the boards, the boards themselves are spawned as entities
+/// using ``Board::new``.
+///
+/// Levels with a time limit are counted down using Bevy's ``Time``, so the plugin depends on the ``TimePlugin``
+/// that is part of both ``DefaultPlugins`` and ``MinimalPlugins``
 pub struct Match3Plugin;
 
 impl Plugin for Match3Plugin {
     fn build(&self, app: &mut App) {
-        let Match3Config {
-            board_dimensions,
-            gem_types,
-        } = app
-            .world_mut()
-            .get_resource::<Match3Config>()
-            .copied()
-            .unwrap_or_default();
-
-        if gem_types < 3 {
-            panic!("Cannot generate board with fewer than 3 different gem types");
-        }
-
-        let mut gems = HashMap::default();
-        (0..board_dimensions.x).for_each(|x| {
-            (0..board_dimensions.y).for_each(|y| {
-                gems.insert([x, y].into(), rand::rng().random_range(0..gem_types));
-            })
-        });
-
-        let mut board = Board {
-            dimensions: board_dimensions,
-            gems,
-            types: (0..gem_types).collect(),
-        };
-
-        board.clear_matches();
-
-        app.insert_resource(board)
-            .insert_resource(BoardCommands::default())
-            .insert_resource(BoardEvents::default())
-            .add_systems(Update, read_commands);
+        // Commands sent before the time ran out are still handled in the same frame
+        app.add_systems(Update, (read_commands, tick_levels).chain());
     }
 }
 
-/// An optional config for the match3 board. This should be inserted as a resource before the `Match3Plugin`
+/// The config used to generate a match3 board with ``Board::new``
 ///
 /// # Examples
 ///
 /// ```
-/// use bevy::prelude::*;
+/// use bevy::{prelude::*, time::TimePlugin};
 /// use bevy_match3::prelude::*;
 ///
 /// App::new()
-///     .insert_resource(Match3Config {
-///         gem_types: 5,
-///         board_dimensions: [10, 10].into(),
+///     .add_plugins((TimePlugin, Match3Plugin))
+///     .add_systems(Startup, |mut commands: Commands| {
+///         commands.spawn(Board::new(&Match3Config {
+///             gem_types: 5,
+///             board_dimensions: [10, 10].into(),
+///             seed: Some(42),
+///             ..default()
+///         }));
 ///     })
-///     .add_plugins(Match3Plugin)
 ///     .run();
 /// ```
-#[derive(Clone, Copy, Resource)]
+#[derive(Clone)]
 pub struct Match3Config {
     /// The number of different gem types the board can spawn
     pub gem_types: u32,
+    /// The gem types together with their weights, like in ``Board::with_gem_weights``.
+    /// Gem types are picked with a chance proportional to their weight, both when generating the board
+    /// and when spawning new gems. Every type has the same weight if this is empty
+    pub gem_weights: Vec<(u32, u32)>,
     /// The rectangular dimensions of the board
     pub board_dimensions: UVec2,
+    /// The playable cells of the board, every cell within the dimensions is playable if this is ``None``.
+    ///
+    /// Cells left out of the mask are voids that never hold a gem and break up any match running through them,
+    /// which allows for boards of any shape and boards with holes
+    pub mask: Option<HashSet<UVec2>>,
+    /// How gems fall past the void cells left out of the mask
+    pub gap_rule: GapRule,
+    /// The direction gems fall in, individual cells can be given their own direction with ``Board::with_cell_gravity``
+    pub gravity: Gravity,
+    /// Whether gems slide in diagonally from the neighboring cells behind an empty cell when nothing can fall straight
+    /// into it, like the cells underneath obstacles. Disabled by default
+    pub diagonal_slide: bool,
+    /// What the board does when it settles with no matching moves left
+    pub deadlock_policy: DeadlockPolicy,
+    /// How the board shuffles its gems when sent a ``BoardCommand::Shuffle`` command
+    pub shuffle_mode: ShuffleMode,
+    /// The seed for the board's random number generator, a random seed is picked if this is ``None``.
+    ///
+    /// The same seed and the same sequence of commands always result in the same board
+    pub seed: Option<u64>,
+    /// The rules deciding which arrangements of gems count as matches
+    pub match_rules: MatchRules,
 }
 
 impl Default for Match3Config {
     fn default() -> Self {
         Self {
             gem_types: 5,
+            gem_weights: vec![],
             board_dimensions: [10, 10].into(),
+            mask: None,
+            gap_rule: GapRule::default(),
+            gravity: Gravity::default(),
+            diagonal_slide: false,
+            deadlock_policy: DeadlockPolicy::default(),
+            shuffle_mode: ShuffleMode::default(),
+            seed: None,
+            match_rules: MatchRules::default(),
         }
     }
 }
@@ -101,7 +148,7 @@ mod tests {
     use bevy::prelude::*;
     use queues::{IsQueue, Queue};
 
-    use crate::{board::*, systems::*};
+    use crate::{board::*, special::*, systems::*, Match3Config};
 
     #[test]
     fn swap_gems() {
@@ -124,18 +171,19 @@ mod tests {
 
         let mut app = App::new();
         app.add_systems(Update, read_commands);
-        app.insert_resource(board.clone());
-        app.insert_resource(BoardCommands(queue));
-        app.insert_resource(BoardEvents::default());
+        let entity = app
+            .world_mut()
+            .spawn((board.clone(), BoardCommands(queue)))
+            .id();
 
         // update
         app.update();
 
         // check
-        assert_ne!(board, *app.world_mut().get_resource::<Board>().unwrap());
+        assert_ne!(board, *app.world().get::<Board>(entity).unwrap());
         assert_eq!(
-            app.world_mut()
-                .get_resource::<Board>()
+            app.world()
+                .get::<Board>(entity)
                 .unwrap()
                 .get(&[1, 2].into())
                 .copied()
@@ -143,8 +191,8 @@ mod tests {
             12
         );
         assert_eq!(
-            app.world_mut()
-                .get_resource::<Board>()
+            app.world()
+                .get::<Board>(entity)
                 .unwrap()
                 .get(&[2, 2].into())
                 .copied()
@@ -174,18 +222,19 @@ mod tests {
 
         let mut app = App::new();
         app.add_systems(Update, read_commands);
-        app.insert_resource(board.clone());
-        app.insert_resource(BoardCommands(queue));
-        app.insert_resource(BoardEvents::default());
+        let entity = app
+            .world_mut()
+            .spawn((board.clone(), BoardCommands(queue)))
+            .id();
 
         // update
         app.update();
 
         // check
-        assert_eq!(board, *app.world_mut().get_resource::<Board>().unwrap());
+        assert_eq!(board, *app.world().get::<Board>(entity).unwrap());
         assert_eq!(
-            app.world_mut()
-                .get_resource::<Board>()
+            app.world()
+                .get::<Board>(entity)
                 .unwrap()
                 .get(&[1, 2].into())
                 .copied()
@@ -193,8 +242,8 @@ mod tests {
             11
         );
         assert_eq!(
-            app.world_mut()
-                .get_resource::<Board>()
+            app.world()
+                .get::<Board>(entity)
                 .unwrap()
                 .get(&[2, 2].into())
                 .copied()
@@ -222,15 +271,16 @@ mod tests {
 
         let mut app = App::new();
         app.add_systems(Update, read_commands);
-        app.insert_resource(board.clone());
-        app.insert_resource(BoardCommands(queue));
-        app.insert_resource(BoardEvents::default());
+        let entity = app
+            .world_mut()
+            .spawn((board.clone(), BoardCommands(queue)))
+            .id();
 
         // update
         app.update();
 
         // check
-        let new_board = app.world_mut().get_resource::<Board>().unwrap();
+        let new_board = app.world().get::<Board>(entity).unwrap();
         assert_ne!(board, *new_board);
         assert_eq!(*new_board.get(&[1, 4].into()).unwrap(), 16);
         assert_eq!(*new_board.get(&[1, 3].into()).unwrap(), 11);
@@ -264,15 +314,16 @@ mod tests {
 
         let mut app = App::new();
         app.add_systems(Update, read_commands);
-        app.insert_resource(board.clone());
-        app.insert_resource(BoardCommands(queue));
-        app.insert_resource(BoardEvents::default());
+        let entity = app
+            .world_mut()
+            .spawn((board.clone(), BoardCommands(queue)))
+            .id();
 
         // Update
         app.update();
 
         // check
-        let new_board = app.world_mut().get_resource::<Board>().unwrap();
+        let new_board = app.world().get::<Board>(entity).unwrap();
         assert_ne!(board, *new_board);
         assert_eq!(*new_board.get(&[3, 6].into()).unwrap(), 18);
         assert_eq!(*new_board.get(&[3, 5].into()).unwrap(), 13);
@@ -308,15 +359,16 @@ mod tests {
 
         let mut app = App::new();
         app.add_systems(Update, read_commands);
-        app.insert_resource(board.clone());
-        app.insert_resource(BoardCommands(queue));
-        app.insert_resource(BoardEvents::default());
+        let entity = app
+            .world_mut()
+            .spawn((board.clone(), BoardCommands(queue)))
+            .id();
 
         // update
         app.update();
 
         // check
-        let new_board = app.world_mut().get_resource::<Board>().unwrap();
+        let new_board = app.world().get::<Board>(entity).unwrap();
         assert_ne!(board, *new_board);
         assert_eq!(*new_board.get(&[0, 5].into()).unwrap(), 20);
         assert_eq!(*new_board.get(&[1, 5].into()).unwrap(), 21);
@@ -325,4 +377,249 @@ mod tests {
         assert!(new_board.get(&[1, 0].into()).is_some());
         assert!(new_board.get(&[2, 0].into()).is_some());
     }
+
+    #[test]
+    fn independent_boards() {
+        // setup
+        #[rustfmt::skip]
+        let board: Board = vec![
+            vec![ 0,  1,  2,  3,  4],
+            vec![ 5,  6,  7,  8,  9],
+            vec![10, 11, 12, 13, 14],
+            vec![15, 16, 11, 18, 19],
+            vec![20, 21, 11, 23, 24],
+            vec![25, 26, 27, 28, 29],
+            vec![30, 31, 32, 33, 34],
+        ].into();
+
+        let mut queue = Queue::default();
+        queue
+            .add(BoardCommand::Swap([1, 2].into(), [2, 2].into()))
+            .unwrap();
+
+        let mut app = App::new();
+        app.add_systems(Update, read_commands);
+        let swapped = app
+            .world_mut()
+            .spawn((board.clone(), BoardCommands(queue)))
+            .id();
+        let untouched = app.world_mut().spawn(board.clone()).id();
+
+        // update
+        app.update();
+
+        // check
+        assert_ne!(board, *app.world().get::<Board>(swapped).unwrap());
+        assert_eq!(board, *app.world().get::<Board>(untouched).unwrap());
+        assert!(matches!(
+            app.world_mut()
+                .get_mut::<BoardEvents>(swapped)
+                .unwrap()
+                .pop(),
+            Ok(BoardEvent::Swapped(_, _))
+        ));
+        assert!(app
+            .world_mut()
+            .get_mut::<BoardEvents>(untouched)
+            .unwrap()
+            .pop()
+            .is_err());
+    }
+
+    #[test]
+    fn seeded_commands() {
+        // setup
+        let config = Match3Config {
+            seed: Some(7),
+            ..Default::default()
+        };
+
+        let mut app = App::new();
+        app.add_systems(Update, read_commands);
+        let boards = [(); 2].map(|_| {
+            let mut queue = Queue::default();
+            queue.add(BoardCommand::Reseed(99)).unwrap();
+            queue
+                .add(BoardCommand::Pop(vec![[0, 9].into(), [4, 4].into()]))
+                .unwrap();
+            queue.add(BoardCommand::Shuffle).unwrap();
+            app.world_mut()
+                .spawn((Board::new(&config), BoardCommands(queue)))
+                .id()
+        });
+
+        // update
+        app.update();
+
+        // check
+        let first = app.world().get::<Board>(boards[0]).unwrap();
+        let second = app.world().get::<Board>(boards[1]).unwrap();
+        assert_eq!(first.seed(), 99);
+        assert_eq!(first, second);
+        assert_ne!(*first, Board::new(&config));
+    }
+
+    #[test]
+    fn pop_match_into_special() {
+        // setup
+        #[rustfmt::skip]
+        let board: Board = vec![
+            vec![ 0,  1,  2,  3,  4],
+            vec![ 5,  6,  7,  8,  9],
+            vec![10, 10, 10, 10, 14],
+            vec![15, 16, 17, 18, 19],
+        ].into();
+
+        let mut queue = Queue::default();
+        queue
+            .add(BoardCommand::Pop(vec![
+                [0, 2].into(),
+                [1, 2].into(),
+                [2, 2].into(),
+                [3, 2].into(),
+            ]))
+            .unwrap();
+
+        let mut app = App::new();
+        app.add_systems(Update, read_commands);
+        let entity = app.world_mut().spawn((board, BoardCommands(queue))).id();
+
+        // update
+        app.update();
+
+        // check
+        let new_board = app.world().get::<Board>(entity).unwrap();
+        assert_eq!(
+            new_board.gem(&[2, 2].into()).unwrap().special(),
+            Some(Special::RowClearer)
+        );
+        assert_eq!(*new_board.get(&[2, 2].into()).unwrap(), 10);
+        let mut events = app.world_mut().get_mut::<BoardEvents>(entity).unwrap();
+        let mut popped = 0;
+        let mut created = vec![];
+        while let Ok(event) = events.pop() {
+            match event {
+                BoardEvent::Popped(_) => popped += 1,
+                BoardEvent::SpecialCreated(pos, special) => created.push((pos, special)),
+                _ => {}
+            }
+        }
+        assert_eq!(popped, 3);
+        assert_eq!(created, vec![([2, 2].into(), Special::RowClearer)]);
+    }
+
+    #[test]
+    fn swap_color_bomb() {
+        // setup
+        #[rustfmt::skip]
+        let mut board: Board = vec![
+            vec![ 0,  1,  2,  3,  4],
+            vec![ 5,  7,  7,  8,  9],
+            vec![10, 11, 12, 13, 14],
+            vec![15,  7, 17, 18,  7],
+        ].into();
+        board.make_special(&[2, 2].into(), Special::ColorBomb);
+
+        let mut queue = Queue::default();
+        queue
+            .add(BoardCommand::Swap([2, 2].into(), [2, 1].into()))
+            .unwrap();
+
+        let mut app = App::new();
+        app.add_systems(Update, read_commands);
+        let entity = app.world_mut().spawn((board, BoardCommands(queue))).id();
+
+        // update
+        app.update();
+
+        // check
+        let mut events = app.world_mut().get_mut::<BoardEvents>(entity).unwrap();
+        assert!(matches!(events.pop(), Ok(BoardEvent::Swapped(_, _))));
+        assert!(matches!(
+            events.pop(),
+            Ok(BoardEvent::SpecialActivated(pos, Special::ColorBomb)) if pos == [2, 1].into()
+        ));
+        let mut popped = vec![];
+        while let Ok(BoardEvent::Popped(pos)) = events.pop() {
+            popped.push(pos);
+        }
+        assert_eq!(popped.len(), 5);
+        assert!(popped.contains(&[2, 1].into()));
+        assert!(popped.contains(&[1, 1].into()));
+        assert!(popped.contains(&[2, 2].into()));
+        assert!(popped.contains(&[1, 3].into()));
+        assert!(popped.contains(&[4, 3].into()));
+    }
+
+    #[test]
+    fn set_gem_weights() {
+        // setup
+        let config = Match3Config {
+            seed: Some(7),
+            ..Default::default()
+        };
+
+        let mut queue = Queue::default();
+        queue
+            .add(BoardCommand::SetGemWeights(vec![(1, 1), (2, 1), (3, 1)]))
+            .unwrap();
+        queue
+            .add(BoardCommand::Pop(vec![[0, 9].into(), [5, 9].into()]))
+            .unwrap();
+
+        let mut app = App::new();
+        app.add_systems(Update, read_commands);
+        let entity = app
+            .world_mut()
+            .spawn((Board::new(&config), BoardCommands(queue)))
+            .id();
+
+        // update
+        app.update();
+
+        // check
+        let board = app.world().get::<Board>(entity).unwrap();
+        assert_eq!(board.gem_weights(), &[(1, 1), (2, 1), (3, 1)]);
+        let mut events = app.world_mut().get_mut::<BoardEvents>(entity).unwrap();
+        let mut spawned = vec![];
+        while let Ok(event) = events.pop() {
+            if let BoardEvent::Spawned(spawns) = event {
+                spawned.extend(spawns);
+            }
+        }
+        assert_eq!(spawned.len(), 2);
+        assert!(spawned.iter().all(|(_, typ)| (1..=3).contains(typ)));
+    }
+
+    #[test]
+    fn reject_gem_weights() {
+        // setup
+        let mut queue = Queue::default();
+        queue
+            .add(BoardCommand::SetGemWeights(vec![(1, 1), (2, 1), (3, 0)]))
+            .unwrap();
+
+        let mut app = App::new();
+        app.add_systems(Update, read_commands);
+        let entity = app
+            .world_mut()
+            .spawn((Board::new(&Match3Config::default()), BoardCommands(queue)))
+            .id();
+
+        // update
+        app.update();
+
+        // check
+        assert!(app
+            .world()
+            .get::<Board>(entity)
+            .unwrap()
+            .gem_weights()
+            .is_empty());
+        let mut events = app.world_mut().get_mut::<BoardEvents>(entity).unwrap();
+        assert!(matches!(
+            events.pop(),
+            Ok(BoardEvent::FailedGemWeights(GemWeightsError::TooFewTypes))
+        ));
+    }
 }
@@ -6,10 +6,90 @@ pub(crate) enum MatchDirection {
 }
 
 /// Represents the different types of matches made
+///
+/// Straight runs of the same type that share a gem are reported as a single shaped match,
+/// where the pivot (.1) is the gem where the runs intersect
 #[derive(Clone, Debug)]
 pub enum Match {
     /// A straight match of 3 or more gems
     Straight(HashSet<UVec2>),
+    /// A horizontal and a vertical match meeting at the end of both
+    LShape(HashSet<UVec2>, UVec2),
+    /// A horizontal and a vertical match where one ends in the middle of the other
+    TShape(HashSet<UVec2>, UVec2),
+    /// A horizontal and a vertical match crossing each other in the middle of both
+    Cross(HashSet<UVec2>, UVec2),
+    /// A 2x2 square of gems, only detected when ``MatchRules::square`` is enabled
+    Square(HashSet<UVec2>),
+}
+
+/// The rules deciding which arrangements of gems count as matches.
+/// Swaps, matching moves and the match free board generation all follow the enabled rules
+///
+/// # Examples
+///
+/// ```
+/// use bevy_match3::prelude::*;
+///
+/// let config = Match3Config {
+///     match_rules: MatchRules {
+///         square: true,
+///         ..Default::default()
+///     },
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub struct MatchRules {
+    /// Straight runs of 3 or more gems, including the L, T and cross shapes they form. Enabled by default
+    pub straight: bool,
+    /// 2x2 squares of gems. Disabled by default
+    pub square: bool,
+}
+
+impl Default for MatchRules {
+    fn default() -> Self {
+        Self {
+            straight: true,
+            square: false,
+        }
+    }
+}
+
+impl Match {
+    /// Returns the coordinates of all gems in this match
+    pub fn gems(&self) -> &HashSet<UVec2> {
+        match self {
+            Match::Straight(gems)
+            | Match::LShape(gems, _)
+            | Match::TShape(gems, _)
+            | Match::Cross(gems, _)
+            | Match::Square(gems) => gems,
+        }
+    }
+
+    /// Returns the gem where the runs of a shaped match intersect, straight matches have no pivot
+    pub fn pivot(&self) -> Option<UVec2> {
+        match self {
+            Match::Straight(_) | Match::Square(_) => None,
+            Match::LShape(_, pivot) | Match::TShape(_, pivot) | Match::Cross(_, pivot) => {
+                Some(*pivot)
+            }
+        }
+    }
+
+    /// Returns the length of the longest straight line of gems in this match
+    pub fn longest_line(&self) -> usize {
+        match self {
+            Match::Square(_) => 2,
+            Match::Straight(gems) => gems.len(),
+            Match::LShape(gems, pivot) | Match::TShape(gems, pivot) | Match::Cross(gems, pivot) => {
+                let row = gems.iter().filter(|pos| pos.y == pivot.y).count();
+                let column = gems.iter().filter(|pos| pos.x == pivot.x).count();
+                row.max(column)
+            }
+        }
+    }
 }
 
 /// A collection of matches
@@ -31,14 +111,96 @@ impl Matches {
     pub fn without_duplicates(&self) -> HashSet<UVec2> {
         self.matches
             .iter()
-            .flat_map(|mat| match mat {
-                Match::Straight(mat) => mat,
-            })
+            .flat_map(|mat| mat.gems())
             .cloned()
             .collect()
     }
 
+    /// Returns an iterator over the matches in this collection
+    pub fn iter(&self) -> impl Iterator<Item = &Match> {
+        self.matches.iter()
+    }
+
     pub(crate) fn is_empty(&self) -> bool {
         self.matches.is_empty()
     }
+
+    /// Merges straight matches sharing a gem into L, T and cross shaped matches
+    pub(crate) fn merge_intersecting(self) -> Matches {
+        let runs = self
+            .matches
+            .into_iter()
+            .map(|mat| mat.gems().clone())
+            .collect::<Vec<_>>();
+
+        // Runs sharing a gem are always of the same type
+        let mut groups: Vec<Vec<usize>> = vec![];
+        for (i, run) in runs.iter().enumerate() {
+            let (touching, mut rest): (Vec<_>, Vec<_>) = groups
+                .into_iter()
+                .partition(|group| group.iter().any(|j| !runs[*j].is_disjoint(run)));
+            let mut merged = touching.into_iter().flatten().collect::<Vec<_>>();
+            merged.push(i);
+            merged.sort();
+            rest.push(merged);
+            groups = rest;
+        }
+        groups.sort();
+
+        let mut matches = Matches::default();
+        for group in groups {
+            if let [single] = group[..] {
+                matches.add(Match::Straight(runs[single].clone()));
+                continue;
+            }
+
+            let mut pivots = group
+                .iter()
+                .flat_map(|i| runs[*i].iter())
+                .filter(|pos| group.iter().filter(|j| runs[**j].contains(*pos)).count() > 1)
+                .copied()
+                .collect::<Vec<_>>();
+            pivots.sort_by_key(|pos| (pos.y, pos.x));
+            let pivot = pivots[0];
+
+            let interior = group
+                .iter()
+                .filter(|i| runs[**i].contains(&pivot))
+                .filter(|i| {
+                    let run = &runs[**i];
+                    let (before, after) = if is_horizontal(run) {
+                        (
+                            pivot.x.checked_sub(1).map(|x| UVec2::new(x, pivot.y)),
+                            UVec2::new(pivot.x + 1, pivot.y),
+                        )
+                    } else {
+                        (
+                            pivot.y.checked_sub(1).map(|y| UVec2::new(pivot.x, y)),
+                            UVec2::new(pivot.x, pivot.y + 1),
+                        )
+                    };
+                    before.is_some_and(|before| run.contains(&before)) && run.contains(&after)
+                })
+                .count();
+
+            let gems = group
+                .iter()
+                .flat_map(|i| runs[*i].iter())
+                .copied()
+                .collect();
+            matches.add(match interior {
+                0 => Match::LShape(gems, pivot),
+                1 => Match::TShape(gems, pivot),
+                _ => Match::Cross(gems, pivot),
+            });
+        }
+        matches
+    }
+}
+
+/// Returns whether all gems in a straight run share the same row
+pub(crate) fn is_horizontal(run: &HashSet<UVec2>) -> bool {
+    let mut ys = run.iter().map(|pos| pos.y);
+    let first = ys.next();
+    ys.all(|y| Some(y) == first)
 }
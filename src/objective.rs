@@ -0,0 +1,184 @@
+use crate::{board::*, level::Level, obstacle::ObstacleKind, systems::BoardEvent};
+
+/// A goal the player has to reach before the limit of a level runs out, see ``Level::with_objective``
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub enum Objective {
+    /// Reach the given total score, which requires the board to have scoring, see ``Board::with_scoring``
+    Score(u64),
+    /// Pop the given number of gems of the given type (.0). Gems turned into specials by a match count as well
+    Collect(u32, u64),
+    /// Destroy every obstacle of the given kind on the board
+    ClearObstacles(ObstacleKind),
+    /// Bring the given number of ingredients to exit cells
+    Ingredients(u64),
+    /// Clear every layer of jelly on the board
+    ClearJelly,
+}
+
+/// How far along an objective is, reported by ``BoardEvent::ObjectiveProgress``
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub struct Progress {
+    /// The amount reached so far, never above the target
+    pub current: u64,
+    /// The amount needed to meet the objective
+    pub target: u64,
+}
+
+impl Progress {
+    /// Returns whether the objective is met
+    pub fn is_done(&self) -> bool {
+        self.current >= self.target
+    }
+}
+
+/// What a single pop counts towards the objectives of a level
+#[derive(Default)]
+pub(crate) struct Tally {
+    /// The types of the popped gems, including the gems turned into specials
+    pub(crate) popped: Vec<u32>,
+    /// The kinds of the destroyed obstacles
+    pub(crate) destroyed: Vec<ObstacleKind>,
+    /// The number of ingredients collected at exit cells
+    pub(crate) ingredients: u64,
+    /// The number of cells cleared of jelly
+    pub(crate) jelly: u64,
+}
+
+impl Objective {
+    /// Returns the progress of the objective on the given board, where score is the score of the level and count
+    /// is what the level has counted towards the objective so far
+    pub(crate) fn progress(&self, board: &Board, score: u64, count: u64) -> Progress {
+        let (current, target) = match self {
+            Objective::Score(target) => (score, *target),
+            Objective::Collect(_, amount) | Objective::Ingredients(amount) => (count, *amount),
+            Objective::ClearObstacles(kind) => {
+                let left = board
+                    .obstacles()
+                    .filter(|(_, obstacle)| obstacle.kind == *kind)
+                    .count() as u64;
+                (count, count + left)
+            }
+            Objective::ClearJelly => (count, count + board.jellies().count() as u64),
+        };
+        Progress {
+            current: current.min(target),
+            target,
+        }
+    }
+
+    /// Returns what the given pop counts towards the objective
+    pub(crate) fn count(&self, tally: &Tally) -> u64 {
+        match self {
+            Objective::Score(_) => 0,
+            Objective::Collect(typ, _) => tally.popped.iter().filter(|t| *t == typ).count() as u64,
+            Objective::ClearObstacles(kind) => {
+                tally.destroyed.iter().filter(|k| *k == kind).count() as u64
+            }
+            Objective::Ingredients(_) => tally.ingredients,
+            Objective::ClearJelly => tally.jelly,
+        }
+    }
+}
+
+impl Level {
+    /// Returns the progress of every objective of the level played on the given board, in the order they were
+    /// added
+    pub fn progress(&self, board: &Board) -> Vec<Progress> {
+        self.objectives
+            .iter()
+            .zip(self.counts.iter())
+            .map(|(objective, count)| objective.progress(board, self.score, *count))
+            .collect()
+    }
+
+    /// Counts the given pop on the given board towards the objectives, returning an event for every objective that
+    /// made progress since the given progress
+    pub(crate) fn track_objectives(
+        &mut self,
+        board: &Board,
+        before: &[Progress],
+        tally: &Tally,
+    ) -> Vec<BoardEvent> {
+        for (objective, count) in self.objectives.iter().zip(self.counts.iter_mut()) {
+            *count += objective.count(tally);
+        }
+        self.progress(board)
+            .into_iter()
+            .enumerate()
+            .filter(|(index, progress)| before.get(*index) != Some(progress))
+            .map(|(index, progress)| BoardEvent::ObjectiveProgress(index, progress))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        level::*,
+        objective::*,
+        obstacle::Obstacle,
+        score::ScoreRules,
+        systems::*,
+        test_util::{cascade_board, events, play},
+    };
+
+    fn progress_events(
+        board: &mut Board,
+        level: &mut Level,
+        command: BoardCommand,
+    ) -> Vec<(usize, Progress)> {
+        events(board, level, command)
+            .into_iter()
+            .filter_map(|event| match event {
+                BoardEvent::ObjectiveProgress(index, progress) => Some((index, progress)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn collect_and_clear() {
+        #[rustfmt::skip]
+        let board: Board = vec![
+            vec![0, 0, 0],
+            vec![1, 2, 3],
+            vec![4, 5, 6],
+        ].into();
+        let mut board = board
+            .with_obstacle([0, 0].into(), Obstacle::new(ObstacleKind::Ice, 1))
+            .with_obstacle([2, 2].into(), Obstacle::new(ObstacleKind::Ice, 1))
+            .with_scoring(ScoreRules::default());
+        let mut level = Level::new(LevelLimit::Moves(10))
+            .with_objective(Objective::Collect(0, 3))
+            .with_objective(Objective::ClearObstacles(ObstacleKind::Ice))
+            .with_objective(Objective::Collect(1, 5))
+            .with_objective(Objective::Score(100));
+
+        let events = progress_events(
+            &mut board,
+            &mut level,
+            BoardCommand::Pop(vec![[0, 0].into(), [1, 0].into(), [2, 0].into()]),
+        );
+
+        let events = events
+            .into_iter()
+            .map(|(index, progress)| (index, progress.current, progress.target))
+            .collect::<Vec<_>>();
+        assert_eq!(events, vec![(0, 3, 3), (1, 1, 2), (3, 60, 100)]);
+        assert!(level.progress(&board)[0].is_done());
+        assert!(!level.progress(&board)[2].is_done());
+    }
+
+    #[test]
+    fn win_by_objectives() {
+        let mut level = Level::new(LevelLimit::Moves(3)).with_objective(Objective::Collect(7, 3));
+
+        play(
+            &mut cascade_board(),
+            &mut level,
+            BoardCommand::Swap([2, 3].into(), [3, 3].into()),
+        );
+
+        assert_eq!(level.state(), LevelState::Won);
+    }
+}
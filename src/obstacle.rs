@@ -0,0 +1,228 @@
+use bevy::math::UVec2;
+
+use crate::{board::*, systems::BoardEvent};
+
+/// An obstacle living on, under or instead of a gem
+///
+/// Each cell holds at most one obstacle, which is destroyed once it has lost all its hit points
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub struct Obstacle {
+    /// The kind of obstacle, which decides how it interacts with gems
+    pub kind: ObstacleKind,
+    /// The number of hits it takes to destroy the obstacle
+    pub hit_points: u32,
+}
+
+impl Obstacle {
+    /// Creates a new obstacle of the given kind with the given number of hit points
+    pub fn new(kind: ObstacleKind, hit_points: u32) -> Self {
+        Self { kind, hit_points }
+    }
+
+    /// Returns whether the obstacle takes up its cell on its own, leaving no room for a gem
+    pub fn occupies_cell(&self) -> bool {
+        matches!(
+            self.kind,
+            ObstacleKind::Crate | ObstacleKind::Stone | ObstacleKind::Hazard
+        )
+    }
+
+    /// Returns whether the obstacle keeps the gem in its cell from being swapped or falling
+    pub fn locks_gem(&self) -> bool {
+        matches!(self.kind, ObstacleKind::Chain)
+    }
+}
+
+/// The different kinds of obstacles
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum ObstacleKind {
+    /// Lies under a gem and takes a hit whenever the gem on top of it is popped
+    Ice,
+    /// Wraps a gem so it can neither be swapped nor fall. Popping the chained gem hits the chain instead
+    Chain,
+    /// Takes up a cell and blocks falling gems, takes a hit from gems popped next to it and from specials
+    Crate,
+    /// Like a crate, but usually with several layers of hit points
+    Stone,
+    /// Like a crate, but spreads into a gem next to it at the end of every turn in which no hazard was destroyed.
+    /// A turn starts with a successful swap and ends once the board has settled
+    Hazard,
+}
+
+impl Board {
+    /// Places an obstacle on the given position, replacing any obstacle already there.
+    /// Obstacles that take up their cell remove the gem in it
+    pub fn with_obstacle(mut self, pos: UVec2, obstacle: Obstacle) -> Self {
+        if obstacle.occupies_cell() {
+            self.remove(&pos);
+        }
+        self.obstacles.insert(pos, obstacle);
+        self
+    }
+
+    /// Returns the obstacle at the given position, if any
+    pub fn obstacle(&self, pos: &UVec2) -> Option<&Obstacle> {
+        self.obstacles.get(pos)
+    }
+
+    /// Returns an iterator over all obstacles on the board
+    pub fn obstacles(&self) -> impl Iterator<Item = (&UVec2, &Obstacle)> {
+        self.obstacles.iter()
+    }
+
+    /// Returns whether the gem at the given position is locked in place by an obstacle
+    pub(crate) fn is_locked(&self, pos: &UVec2) -> bool {
+        self.obstacle(pos).is_some_and(Obstacle::locks_gem)
+    }
+
+    /// Returns whether the given position is taken up by an obstacle
+    pub(crate) fn is_blocked(&self, pos: &UVec2) -> bool {
+        self.obstacle(pos).is_some_and(Obstacle::occupies_cell)
+    }
+
+    /// Takes a hit point from the obstacle at the given position, returning the event describing the hit
+    pub(crate) fn hit_obstacle(&mut self, pos: &UVec2) -> Option<BoardEvent> {
+        let obstacle = self.obstacles.get_mut(pos)?;
+        obstacle.hit_points = obstacle.hit_points.saturating_sub(1);
+        let obstacle = *obstacle;
+        if obstacle.hit_points == 0 {
+            self.obstacles.remove(pos);
+            Some(BoardEvent::ObstacleDestroyed(*pos, obstacle.kind))
+        } else {
+            Some(BoardEvent::ObstacleDamaged(*pos, obstacle))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::UVec2;
+
+    use crate::{level::Level, obstacle::*, systems::*, test_util::events};
+
+    #[test]
+    fn chained_gem_cannot_swap() {
+        #[rustfmt::skip]
+        let mut board: Board = vec![
+            vec![ 0,  1,  2,  3,  4],
+            vec![ 5,  6,  7,  8,  9],
+            vec![10, 11, 12, 13, 14],
+            vec![15, 16, 11, 18, 19],
+            vec![20, 21, 11, 23, 24],
+        ].into();
+        board = board.with_obstacle([1, 2].into(), Obstacle::new(ObstacleKind::Chain, 1));
+
+        let events = events(
+            &mut board,
+            &mut Level::default(),
+            BoardCommand::Swap([1, 2].into(), [2, 2].into()),
+        );
+
+        assert!(matches!(
+            events[..],
+            [BoardEvent::FailedSwap(_, _, SwapError::Locked(_))]
+        ));
+        assert!(board.get_matching_moves().is_empty());
+    }
+
+    #[test]
+    fn chained_gem_is_freed_instead_of_popped() {
+        #[rustfmt::skip]
+        let mut board: Board = vec![
+            vec![ 0,  1,  2],
+            vec![ 3,  4,  5],
+            vec![ 6,  7,  8],
+        ].into();
+        board = board.with_obstacle([1, 1].into(), Obstacle::new(ObstacleKind::Chain, 1));
+
+        let events = events(
+            &mut board,
+            &mut Level::default(),
+            BoardCommand::Pop(vec![[1, 1].into(), [1, 2].into()]),
+        );
+
+        assert!(events.iter().any(|event| matches!(
+            event,
+            BoardEvent::ObstacleDestroyed(pos, ObstacleKind::Chain) if *pos == [1, 1].into()
+        )));
+        assert!(board.obstacle(&[1, 1].into()).is_none());
+        assert_eq!(*board.get(&[1, 2].into()).unwrap(), 4);
+    }
+
+    #[test]
+    fn ice_cracks_under_popped_gem() {
+        #[rustfmt::skip]
+        let mut board: Board = vec![
+            vec![ 0,  1,  2],
+            vec![ 3,  4,  5],
+            vec![ 6,  7,  8],
+        ].into();
+        board = board.with_obstacle([2, 2].into(), Obstacle::new(ObstacleKind::Ice, 2));
+
+        let events = events(
+            &mut board,
+            &mut Level::default(),
+            BoardCommand::Pop(vec![[2, 2].into()]),
+        );
+
+        let damaged = events.iter().find_map(|event| match event {
+            BoardEvent::ObstacleDamaged(pos, obstacle) => Some((*pos, *obstacle)),
+            _ => None,
+        });
+        assert_eq!(
+            damaged,
+            Some(([2, 2].into(), Obstacle::new(ObstacleKind::Ice, 1)))
+        );
+        assert_eq!(board.obstacle(&[2, 2].into()).unwrap().hit_points, 1);
+    }
+
+    #[test]
+    fn crate_breaks_next_to_pop_and_blocks_drops() {
+        #[rustfmt::skip]
+        let mut board: Board = vec![
+            vec![ 0,  1,  2],
+            vec![ 3,  4,  5],
+            vec![ 6,  7,  8],
+        ].into();
+        board = board
+            .with_obstacle([0, 1].into(), Obstacle::new(ObstacleKind::Crate, 1))
+            .with_obstacle([1, 1].into(), Obstacle::new(ObstacleKind::Stone, 2));
+
+        let events = events(
+            &mut board,
+            &mut Level::default(),
+            BoardCommand::Pop(vec![[0, 2].into(), [1, 2].into()]),
+        );
+
+        assert!(events.iter().any(|event| matches!(
+            event,
+            BoardEvent::ObstacleDestroyed(pos, ObstacleKind::Crate) if *pos == [0, 1].into()
+        )));
+        assert_eq!(board.obstacle(&[1, 1].into()).unwrap().hit_points, 1);
+        // The gem above the stone can't fall past it
+        assert_eq!(*board.get(&[1, 0].into()).unwrap(), 1);
+        assert!(board.get(&[1, 1].into()).is_none());
+        assert!(board.get(&[1, 2].into()).is_some());
+        let dropped = events
+            .iter()
+            .find_map(|event| match event {
+                BoardEvent::Dropped(drops) => Some(drops.clone()),
+                _ => None,
+            })
+            .unwrap();
+        // Once the crate is gone, the gem above it falls as usual
+        assert_eq!(dropped, vec![([0, 0].into(), [0, 2].into()).into()]);
+        let spawned = events
+            .iter()
+            .find_map(|event| match event {
+                BoardEvent::Spawned(spawns) => {
+                    Some(spawns.iter().map(|(pos, _)| *pos).collect::<Vec<UVec2>>())
+                }
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(spawned.len(), 3);
+        assert!(spawned.contains(&[0, 0].into()));
+        assert!(spawned.contains(&[0, 1].into()));
+    }
+}
@@ -0,0 +1,286 @@
+use bevy::{math::UVec2, platform::collections::HashSet};
+
+use crate::{board::*, level::Level, mat::Match, special::Special};
+
+/// The points a board with scoring awards for matches and activated specials, see ``Board::with_scoring``
+///
+/// # Examples
+///
+/// ```
+/// use bevy_match3::prelude::*;
+///
+/// let board = Board::new(&Match3Config::default()).with_scoring(ScoreRules {
+///     straight: vec![30, 60, 100],
+///     cascade_multipliers: vec![1, 2],
+///     ..Default::default()
+/// });
+/// ```
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct ScoreRules {
+    /// The points for straight matches of 3, 4, 5 and more gems. Matches longer than the list use its last entry
+    pub straight: Vec<u32>,
+    /// The points for L shaped matches
+    pub l_shape: u32,
+    /// The points for T shaped matches
+    pub t_shape: u32,
+    /// The points for cross shaped matches
+    pub cross: u32,
+    /// The points for 2x2 square matches
+    pub square: u32,
+    /// The multipliers for each round of matches popped since the last swap. The matches made by the swap itself
+    /// use the first entry, and cascades deeper than the list use its last entry
+    pub cascade_multipliers: Vec<u32>,
+    /// The points for activating a row or column clearer
+    pub clearer: u32,
+    /// The points for activating a bomb
+    pub bomb: u32,
+    /// The points for activating a color bomb
+    pub color_bomb: u32,
+}
+
+impl Default for ScoreRules {
+    fn default() -> Self {
+        Self {
+            straight: vec![60, 120, 200],
+            l_shape: 150,
+            t_shape: 180,
+            cross: 200,
+            square: 80,
+            cascade_multipliers: vec![1, 2, 3, 4, 5],
+            clearer: 100,
+            bomb: 150,
+            color_bomb: 300,
+        }
+    }
+}
+
+impl ScoreRules {
+    /// Returns the points for the given match before any multiplier
+    pub fn match_points(&self, mat: &Match) -> u32 {
+        match mat {
+            Match::Straight(gems) => self
+                .straight
+                .get(gems.len().saturating_sub(3))
+                .or(self.straight.last())
+                .copied()
+                .unwrap_or_default(),
+            Match::LShape(..) => self.l_shape,
+            Match::TShape(..) => self.t_shape,
+            Match::Cross(..) => self.cross,
+            Match::Square(_) => self.square,
+        }
+    }
+
+    /// Returns the points for activating the given special before any multiplier
+    pub fn special_points(&self, special: Special) -> u32 {
+        match special {
+            Special::RowClearer | Special::ColumnClearer => self.clearer,
+            Special::Bomb => self.bomb,
+            Special::ColorBomb => self.color_bomb,
+        }
+    }
+
+    /// Returns the multiplier for the given round of matches since the last swap, starting from 1
+    pub fn multiplier(&self, cascade: u32) -> u32 {
+        self.cascade_multipliers
+            .get(cascade.saturating_sub(1) as usize)
+            .or(self.cascade_multipliers.last())
+            .copied()
+            .unwrap_or(1)
+    }
+}
+
+/// Points awarded by a board with scoring, reported by ``BoardEvent::Scored``
+#[derive(Clone, Debug)]
+pub struct Score {
+    /// The points before the multiplier
+    pub points: u32,
+    /// The multiplier of the round of matches the points were scored in
+    pub multiplier: u32,
+    /// What the points were awarded for
+    pub reason: ScoreReason,
+}
+
+impl Score {
+    /// Returns the points after the multiplier, which is what is added to ``Level::score``
+    pub fn total(&self) -> u64 {
+        u64::from(self.points) * u64::from(self.multiplier)
+    }
+}
+
+/// What points were awarded for
+#[derive(Clone, Debug)]
+pub enum ScoreReason {
+    /// A match was popped
+    Match(Match),
+    /// A special gem was activated
+    Special(UVec2, Special),
+}
+
+impl Board {
+    /// Enables scoring with the given rules, making every pop of a match or special send a ``BoardEvent::Scored``
+    /// event and add to ``Level::score``
+    pub fn with_scoring(mut self, rules: ScoreRules) -> Self {
+        self.scoring = Some(rules);
+        self
+    }
+
+    /// Returns the rules the board scores by, or ``None`` if scoring is disabled
+    pub fn scoring(&self) -> Option<&ScoreRules> {
+        self.scoring.as_ref()
+    }
+
+    /// Returns the matches on the board completely popped by the given positions, or nothing if scoring is disabled
+    pub(crate) fn scored_matches(&self, popped: &HashSet<UVec2>) -> Vec<Match> {
+        if self.scoring.is_none() {
+            return vec![];
+        }
+        self.get_matches()
+            .iter()
+            .filter(|mat| mat.gems().iter().all(|pos| popped.contains(pos)))
+            .cloned()
+            .collect()
+    }
+}
+
+impl Level {
+    /// Returns the total points scored on the board so far
+    pub fn score(&self) -> u64 {
+        self.score
+    }
+
+    /// Scores the popped matches and activated specials by the given rules as the next round of matches since the
+    /// last swap, adding them to the total score
+    pub(crate) fn score_pop(
+        &mut self,
+        rules: Option<&ScoreRules>,
+        matches: Vec<Match>,
+        activated: &[(UVec2, Special)],
+    ) -> Vec<Score> {
+        let Some(rules) = rules else {
+            return vec![];
+        };
+        if matches.is_empty() && activated.is_empty() {
+            return vec![];
+        }
+        self.cascade += 1;
+        let multiplier = rules.multiplier(self.cascade);
+        let scores = matches
+            .into_iter()
+            .map(|mat| Score {
+                points: rules.match_points(&mat),
+                multiplier,
+                reason: ScoreReason::Match(mat),
+            })
+            .chain(activated.iter().map(|(pos, special)| Score {
+                points: rules.special_points(*special),
+                multiplier,
+                reason: ScoreReason::Special(*pos, *special),
+            }))
+            .collect::<Vec<_>>();
+        self.score += scores.iter().map(Score::total).sum::<u64>();
+        scores
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        score::*,
+        systems::*,
+        test_util::{cascade_board, events, play},
+    };
+
+    #[test]
+    fn score_cascade() {
+        let mut board = cascade_board().with_scoring(ScoreRules::default());
+        let mut level = Level::default();
+
+        let scores = play(
+            &mut board,
+            &mut level,
+            BoardCommand::Swap([2, 3].into(), [3, 3].into()),
+        )
+        .into_iter()
+        .filter_map(|event| match event {
+            BoardEvent::Scored(score) => Some(score),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+        // The column of 7s, then the row of 0s it drops into place
+        assert!(scores.len() >= 2);
+        assert_eq!((scores[0].points, scores[0].multiplier), (60, 1));
+        assert!(matches!(
+            scores[0].reason,
+            ScoreReason::Match(Match::Straight(_))
+        ));
+        assert_eq!((scores[1].points, scores[1].multiplier), (60, 2));
+        assert_eq!(level.score(), scores.iter().map(Score::total).sum::<u64>());
+    }
+
+    #[test]
+    fn pop_after_turn() {
+        let mut board = cascade_board().with_scoring(ScoreRules::default());
+        let mut level = Level::default();
+        play(
+            &mut board,
+            &mut level,
+            BoardCommand::Swap([2, 3].into(), [3, 3].into()),
+        );
+        board.make_special(&[0, 0].into(), Special::Bomb);
+
+        let events = play(
+            &mut board,
+            &mut level,
+            BoardCommand::Pop(vec![[0, 0].into()]),
+        );
+
+        let Some(BoardEvent::Scored(score)) = events
+            .into_iter()
+            .find(|event| matches!(event, BoardEvent::Scored(_)))
+        else {
+            panic!("popping a bomb should score");
+        };
+        assert!(matches!(
+            score.reason,
+            ScoreReason::Special(_, Special::Bomb)
+        ));
+        assert_eq!(score.multiplier, 1);
+    }
+
+    #[test]
+    fn no_scoring() {
+        #[rustfmt::skip]
+        let mut board: Board = vec![
+            vec![0, 0, 0],
+            vec![1, 2, 3],
+            vec![4, 5, 6],
+        ].into();
+        let mut level = Level::default();
+
+        let events = events(
+            &mut board,
+            &mut level,
+            BoardCommand::Pop(vec![[0, 0].into(), [1, 0].into(), [2, 0].into()]),
+        );
+
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, BoardEvent::Scored(_))));
+        assert_eq!(level.score(), 0);
+    }
+
+    #[test]
+    fn rules() {
+        let rules = ScoreRules::default();
+        let line = |len: u32| Match::Straight((0..len).map(|x| UVec2::new(x, 0)).collect());
+
+        assert_eq!(rules.match_points(&line(3)), 60);
+        assert_eq!(rules.match_points(&line(4)), 120);
+        assert_eq!(rules.match_points(&line(7)), 200);
+        assert_eq!(rules.special_points(Special::ColumnClearer), 100);
+        assert_eq!(rules.multiplier(1), 1);
+        assert_eq!(rules.multiplier(9), 5);
+    }
+}
@@ -0,0 +1,211 @@
+use bevy::{math::UVec2, platform::collections::HashMap};
+use rand::{prelude::SliceRandom, RngExt};
+
+use crate::board::*;
+
+/// The number of shuffles tried before giving up on finding a playable one
+const SHUFFLE_ATTEMPTS: usize = 100;
+/// The number of gems moved out of matches in a single shuffle before trying a new one
+const REPAIR_ATTEMPTS: usize = 200;
+
+/// Decides how a board shuffles its gems.
+///
+/// Chained gems never move in a shuffle, and special gems only move if the board allows it with
+/// ``Board::with_shuffle_specials``
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default)]
+pub enum ShuffleMode {
+    /// Moves every gem to a random position, which may result in matches and boards with no matching moves
+    Random,
+    /// Moves the gems so that the board has no matches and at least one matching move.
+    /// Falls back to a random shuffle if no such arrangement is found
+    #[default]
+    Playable,
+}
+
+impl Board {
+    /// Replaces how the board shuffles its gems
+    pub fn with_shuffle_mode(mut self, shuffle_mode: ShuffleMode) -> Self {
+        self.shuffle_mode = shuffle_mode;
+        self
+    }
+
+    /// Returns how the board shuffles its gems
+    pub fn shuffle_mode(&self) -> ShuffleMode {
+        self.shuffle_mode
+    }
+
+    /// Decides whether special gems are moved around by shuffles, or stay in place like chained gems do
+    pub fn with_shuffle_specials(mut self, shuffle_specials: bool) -> Self {
+        self.shuffle_specials = shuffle_specials;
+        self
+    }
+
+    /// Returns the positions of the gems a shuffle may move in board order, see ``BoardRng``.
+    /// Chained gems always stay in place, and cells taken up by obstacles never hold a gem to begin with
+    fn shufflable(&self) -> Vec<UVec2> {
+        let mut positions = self
+            .gems
+            .iter()
+            .filter(|(pos, gem)| {
+                !self.is_locked(pos) && (self.shuffle_specials || gem.special().is_none())
+            })
+            .map(|(pos, _)| *pos)
+            .collect::<Vec<_>>();
+        positions.sort_by_key(|pos| (pos.x, pos.y));
+        positions
+    }
+
+    /// Shuffles the board following its shuffle mode, returning the moves from the old to the new positions
+    pub(crate) fn shuffle(&mut self) -> Vec<(UVec2, UVec2)> {
+        match self.shuffle_mode {
+            ShuffleMode::Random => self.permute(),
+            ShuffleMode::Playable => self.playable_shuffle().unwrap_or_else(|| self.permute()),
+        }
+    }
+
+    /// Moves every shufflable gem to a random shufflable position, returning the moves from the old to the new
+    /// positions
+    fn permute(&mut self) -> Vec<(UVec2, UVec2)> {
+        let gems = self.gems.clone();
+        let keys = self.shufflable();
+        let mut values = keys.iter().map(|key| (*key, gems[key])).collect::<Vec<_>>();
+        let mut moves = Vec::with_capacity(keys.len());
+        values.shuffle(&mut self.rng);
+        for ((old_key, value), new_key) in values.into_iter().zip(keys) {
+            self.gems.insert(new_key, value);
+            moves.push((old_key, new_key));
+        }
+        moves
+    }
+
+    /// Shuffles the board into one with no matches and at least one matching move, returning the moves from the
+    /// old to the new positions. Leaves the board as it was if no such shuffle is found
+    pub(crate) fn playable_shuffle(&mut self) -> Option<Vec<(UVec2, UVec2)>> {
+        let before = self.gems.clone();
+        let positions = self.shufflable();
+        if positions.is_empty() {
+            return None;
+        }
+        for _ in 0..SHUFFLE_ATTEMPTS {
+            self.gems = before.clone();
+            // Where the gem now at each position was before the shuffle
+            let mut origins = self
+                .permute()
+                .into_iter()
+                .map(|(from, to)| (to, from))
+                .collect::<HashMap<_, _>>();
+            // A random shuffle of a full board almost always has matches, so matched gems are swapped away
+            // with random other gems until there are none left
+            for _ in 0..REPAIR_ATTEMPTS {
+                let mut matched = self
+                    .get_matches()
+                    .without_duplicates()
+                    .into_iter()
+                    .filter(|pos| positions.contains(pos))
+                    .collect::<Vec<_>>();
+                if matched.is_empty() {
+                    break;
+                }
+                matched.sort_by_key(|pos| (pos.x, pos.y));
+                let pos = matched[self.rng.random_range(0..matched.len())];
+                let other = positions[self.rng.random_range(0..positions.len())];
+                let (gem, other_gem) = (self.gems[&pos], self.gems[&other]);
+                self.gems.insert(pos, other_gem);
+                self.gems.insert(other, gem);
+                let (origin, other_origin) = (origins[&pos], origins[&other]);
+                origins.insert(pos, other_origin);
+                origins.insert(other, origin);
+            }
+            if self.get_matches().is_empty() && !self.get_matching_moves().is_empty() {
+                let mut moves = origins
+                    .into_iter()
+                    .map(|(to, from)| (from, to))
+                    .collect::<Vec<_>>();
+                moves.sort_by_key(|(from, _)| (from.x, from.y));
+                return Some(moves);
+            }
+        }
+        self.gems = before;
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::platform::collections::HashMap;
+
+    use crate::{
+        level::Level, obstacle::*, shuffle::*, special::Special, systems::*, Match3Config,
+    };
+
+    fn type_counts(board: &Board) -> HashMap<u32, usize> {
+        let mut counts = HashMap::default();
+        for (_, typ) in board.iter() {
+            *counts.entry(*typ).or_default() += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn playable_shuffle() {
+        for seed in 0..10 {
+            let mut board = Board::new(&Match3Config {
+                seed: Some(seed),
+                ..Default::default()
+            });
+            let counts = type_counts(&board);
+            let mut events = BoardEvents::default();
+
+            handle_command(
+                &mut board,
+                &mut Level::default(),
+                BoardCommand::Shuffle,
+                &mut events,
+            );
+
+            assert!(matches!(events.pop(), Ok(BoardEvent::Shuffled(moves)) if moves.len() == 100));
+            assert!(events.pop().is_err());
+            assert!(board.get_matches().is_empty());
+            assert!(!board.get_matching_moves().is_empty());
+            assert_eq!(type_counts(&board), counts);
+        }
+    }
+
+    #[test]
+    fn shuffle_around_fixed_gems() {
+        let mut board = Board::new(&Match3Config {
+            seed: Some(5),
+            ..Default::default()
+        })
+        .with_obstacle([2, 2].into(), Obstacle::new(ObstacleKind::Chain, 1))
+        .with_obstacle([5, 5].into(), Obstacle::new(ObstacleKind::Crate, 1))
+        .with_shuffle_specials(false);
+        board.make_special(&[7, 7].into(), Special::Bomb);
+        let before = board.clone();
+
+        let moves = board.shuffle();
+
+        assert_eq!(moves.len(), 97);
+        for pos in [[2, 2], [5, 5], [7, 7]].map(UVec2::from) {
+            assert!(moves.iter().all(|(from, to)| *from != pos && *to != pos));
+            assert_eq!(board.gem(&pos), before.gem(&pos));
+        }
+        assert!(board.get_matches().is_empty());
+        assert!(!board.get_matching_moves().is_empty());
+    }
+
+    #[test]
+    fn shuffled_moves_match_board() {
+        let mut board = Board::new(&Match3Config {
+            seed: Some(3),
+            ..Default::default()
+        });
+        let before = board.clone();
+
+        let moves = board.shuffle();
+
+        for (from, to) in moves {
+            assert_eq!(before.get(&from), board.get(&to));
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use crate::{board::*, level::Level, special::Special, systems::*};
+
+/// The most rounds of matches played out by a single simulation
+const SIMULATION_ROUNDS: usize = 100;
+
+/// The outcome of playing out a move with ``Board::simulate``
+#[derive(Clone, Debug)]
+pub struct Simulation {
+    /// The move that was simulated
+    pub board_move: BoardMove,
+    /// The number of rounds of matches popped, the matches made by the move itself included
+    pub cascades: usize,
+    /// The number of gems popped over all rounds
+    pub cleared: usize,
+    /// The number of special gems created over all rounds
+    pub specials_created: usize,
+    /// The number of special gems activated over all rounds
+    pub specials_activated: usize,
+    /// The number of hits taken by obstacles over all rounds
+    pub obstacles_hit: usize,
+    /// The progress made towards each objective of the level over all rounds, in the order of
+    /// ``Level::objectives``. Objectives that were already met make no more progress
+    pub objectives: Vec<u64>,
+    /// The board once everything has settled
+    pub board: Board,
+    /// The level once everything has settled, which tells whether the move wins or loses it
+    pub level: Level,
+}
+
+impl Board {
+    /// Plays out the given move and every cascade following it on a copy of the board and the given level, popping
+    /// every match as soon as it is made. The copy is re-seeded with the given seed, so the same seed always spawns
+    /// the same gems.
+    ///
+    /// Returns ``None`` if the move is not a legal swap
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bevy_match3::prelude::*;
+    ///
+    /// let board = Board::new(&Match3Config::default());
+    /// let level = Level::default();
+    /// // Averaging over a few seeds estimates the expected outcome of a move
+    /// if let Some(board_move) = board.get_matching_moves().into_iter().next() {
+    ///     let cascades = (0..10)
+    ///         .filter_map(|seed| board.simulate(&level, board_move, seed))
+    ///         .map(|simulation| simulation.cascades)
+    ///         .sum::<usize>() as f32
+    ///         / 10.0;
+    /// }
+    /// ```
+    pub fn simulate(&self, level: &Level, board_move: BoardMove, seed: u64) -> Option<Simulation> {
+        let BoardMove(pos1, pos2) = board_move;
+        let mut board = self.clone();
+        let mut level = level.clone();
+        board.reseed(seed);
+        // Swapping a color bomb pops it right away without reporting a match
+        let color_bomb = [pos1, pos2]
+            .iter()
+            .any(|pos| board.gem(pos).and_then(Gem::special) == Some(Special::ColorBomb));
+
+        let mut events = BoardEvents::default();
+        handle_command(
+            &mut board,
+            &mut level,
+            BoardCommand::Swap(pos1, pos2),
+            &mut events,
+        );
+
+        let before = level.progress(&board);
+        let mut objectives = vec![0; before.len()];
+        let mut cascades = usize::from(color_bomb);
+        let (mut cleared, mut specials_created, mut specials_activated, mut obstacles_hit) =
+            (0, 0, 0, 0);
+        while let Ok(event) = events.pop() {
+            match event {
+                BoardEvent::FailedSwap(_, _, _) => return None,
+                BoardEvent::Matched(matches) if cascades < SIMULATION_ROUNDS => {
+                    cascades += 1;
+                    let mut gems = matches.without_duplicates().into_iter().collect::<Vec<_>>();
+                    gems.sort_by_key(|pos| (pos.y, pos.x));
+                    handle_command(&mut board, &mut level, BoardCommand::Pop(gems), &mut events);
+                }
+                BoardEvent::Popped(_) => cleared += 1,
+                BoardEvent::SpecialCreated(_, _) => specials_created += 1,
+                BoardEvent::SpecialActivated(_, _) => specials_activated += 1,
+                BoardEvent::ObstacleDamaged(_, _) | BoardEvent::ObstacleDestroyed(_, _) => {
+                    obstacles_hit += 1
+                }
+                BoardEvent::ObjectiveProgress(index, progress) => {
+                    objectives[index] = progress.current.saturating_sub(before[index].current)
+                }
+                _ => {}
+            }
+        }
+        Some(Simulation {
+            board_move,
+            cascades,
+            cleared,
+            specials_created,
+            specials_activated,
+            obstacles_hit,
+            objectives,
+            board,
+            level,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{board::*, level::*, objective::Objective, test_util::cascade_board, Match3Config};
+
+    #[test]
+    fn simulate_cascade() {
+        let board = cascade_board();
+
+        let simulation = board
+            .simulate(
+                &Level::default(),
+                BoardMove([2, 3].into(), [3, 3].into()),
+                0,
+            )
+            .unwrap();
+
+        // Popping the column of 7s drops a 0 next to the other two
+        assert!(simulation.cascades >= 2);
+        assert!(simulation.cleared >= 6);
+        assert!(simulation.board.get_matches().is_empty());
+        assert!(board
+            .simulate(
+                &Level::default(),
+                BoardMove([0, 0].into(), [1, 0].into()),
+                0
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn simulate_with_seed() {
+        let board = Board::new(&Match3Config {
+            seed: Some(11),
+            ..Default::default()
+        });
+        let board_move = board.hints()[0].board_move;
+
+        let first = board.simulate(&Level::default(), board_move, 1).unwrap();
+        let second = board.simulate(&Level::default(), board_move, 1).unwrap();
+
+        assert_eq!(first.board, second.board);
+        assert_eq!(first.cleared, second.cleared);
+    }
+
+    #[test]
+    fn simulate_objectives() {
+        let level = Level::new(LevelLimit::Moves(5))
+            .with_objective(Objective::Collect(7, 3))
+            .with_objective(Objective::Collect(0, 10));
+        let winning = Level::new(LevelLimit::Moves(5)).with_objective(Objective::Collect(7, 3));
+
+        let simulation = cascade_board()
+            .simulate(&level, BoardMove([2, 3].into(), [3, 3].into()), 0)
+            .unwrap();
+
+        // The column of 7s meets the first objective, the row of 0s it drops into place counts towards the second
+        assert_eq!(simulation.objectives[0], 3);
+        assert!(simulation.objectives[1] >= 3);
+        assert_eq!(simulation.level.state(), LevelState::Playing);
+        let simulation = cascade_board()
+            .simulate(&winning, BoardMove([2, 3].into(), [3, 3].into()), 0)
+            .unwrap();
+        assert_eq!(simulation.level.state(), LevelState::Won);
+        assert_eq!(winning.state(), LevelState::Playing);
+    }
+}
@@ -0,0 +1,172 @@
+use bevy::math::UVec2;
+use rand::RngExt;
+
+use crate::board::*;
+
+/// A cell new gems spawn in, each with its own table of gem types to pick from.
+///
+/// Once a board has any spawners, new gems only appear in its spawner cells and fall into the rest of the board
+/// from there
+///
+/// # Examples
+///
+/// ```
+/// use bevy_match3::prelude::*;
+///
+/// // The top cell of column 3 spawns type 0 three times as often as types 1 and 2
+/// let board = Board::new(&Match3Config::default())
+///     .with_spawner([3, 0].into(), Spawner::new(vec![(0, 3), (1, 1), (2, 1)]));
+/// ```
+#[derive(Eq, PartialEq, Debug, Clone, Default)]
+pub struct Spawner {
+    /// The gem types this spawner picks from together with their weights.
+    /// An empty table picks from the board's gem types instead
+    pub table: Vec<(u32, u32)>,
+    /// The ingredient types this spawner spawns together with the chance in percent of each spawn being that
+    /// ingredient. Empty by default
+    pub ingredients: Vec<(u32, u32)>,
+}
+
+impl Spawner {
+    /// Creates a new spawner picking from the given gem types and weights
+    pub fn new(table: Vec<(u32, u32)>) -> Self {
+        Self {
+            table,
+            ingredients: vec![],
+        }
+    }
+
+    /// Makes the spawner spawn the given ingredient types, each with the given chance in percent
+    pub fn with_ingredients(mut self, ingredients: Vec<(u32, u32)>) -> Self {
+        self.ingredients = ingredients;
+        self
+    }
+}
+
+impl Board {
+    /// Turns the given position into a spawner cell
+    pub fn with_spawner(mut self, pos: UVec2, spawner: Spawner) -> Self {
+        self.spawners.insert(pos, spawner);
+        self
+    }
+
+    /// Returns the spawner at the given position, if any
+    pub fn spawner(&self, pos: &UVec2) -> Option<&Spawner> {
+        self.spawners.get(pos)
+    }
+
+    /// Picks a new gem spawning at the given position, which may be an ingredient
+    pub(crate) fn spawn_gem(&mut self, pos: &UVec2) -> Gem {
+        let Some(spawner) = self.spawners.get(pos) else {
+            return self.random_type().into();
+        };
+        if !spawner.ingredients.is_empty() {
+            let mut roll = self.rng.random_range(0..100);
+            for (typ, chance) in spawner.ingredients.iter() {
+                if roll < *chance {
+                    return Gem {
+                        typ: *typ,
+                        kind: GemKind::Ingredient,
+                    };
+                }
+                roll -= chance;
+            }
+        }
+        match pick_weighted(&mut self.rng, &spawner.table) {
+            Some(typ) => typ.into(),
+            None => self.random_type().into(),
+        }
+    }
+}
+
+/// Picks one of the given values, each with a chance proportional to its weight, or ``None`` if the table is empty
+pub(crate) fn pick_weighted(rng: &mut BoardRng, table: &[(u32, u32)]) -> Option<u32> {
+    if table.is_empty() {
+        return None;
+    }
+    let total = table
+        .iter()
+        .map(|(_, weight)| u64::from(*weight))
+        .sum::<u64>();
+    if total == 0 {
+        return Some(table[rng.random_range(0..table.len())].0);
+    }
+    let mut roll = rng.random_range(0..total);
+    for (value, weight) in table {
+        if roll < u64::from(*weight) {
+            return Some(*value);
+        }
+        roll -= u64::from(*weight);
+    }
+    unreachable!()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{level::Level, spawner::*, systems::*};
+
+    #[test]
+    fn spawn_only_from_spawners() {
+        #[rustfmt::skip]
+        let mut board: Board = vec![
+            vec![0, 1, 2],
+            vec![3, 4, 5],
+            vec![6, 7, 8],
+        ].into();
+        board = board.with_spawner([1, 0].into(), Spawner::new(vec![(9, 1)]));
+        let mut events = BoardEvents::default();
+
+        handle_command(
+            &mut board,
+            &mut Level::default(),
+            BoardCommand::Pop(vec![[0, 2].into(), [1, 1].into(), [1, 2].into()]),
+            &mut events,
+        );
+
+        let mut spawns = vec![];
+        let mut drops = vec![];
+        while let Ok(event) = events.pop() {
+            match event {
+                BoardEvent::Spawned(spawned) => spawns.extend(spawned),
+                BoardEvent::Dropped(dropped) => drops.extend(dropped),
+                _ => {}
+            }
+        }
+
+        assert_eq!(spawns, vec![([1, 0].into(), 9), ([1, 0].into(), 9)]);
+        assert!(drops.contains(&([1, 0].into(), [1, 2].into()).into()));
+        assert_eq!(*board.get(&[1, 2].into()).unwrap(), 1);
+        assert_eq!(*board.get(&[1, 1].into()).unwrap(), 9);
+        assert_eq!(*board.get(&[1, 0].into()).unwrap(), 9);
+        // Without a spawner on top, the column is left with a hole
+        assert!(board.get(&[0, 0].into()).is_none());
+    }
+
+    #[test]
+    fn weighted_table() {
+        let mut board: Board = vec![vec![0, 1, 2]].into();
+        board.reseed(7);
+        let table = [(0, 0), (1, 1), (2, 3)];
+
+        let picks = (0..400)
+            .map(|_| pick_weighted(&mut board.rng, &table).unwrap())
+            .collect::<Vec<_>>();
+
+        assert!(!picks.contains(&0));
+        let twos = picks.iter().filter(|typ| **typ == 2).count();
+        assert!((250..350).contains(&twos));
+    }
+
+    #[test]
+    fn extreme_tables() {
+        let mut board: Board = vec![vec![0, 1, 2]].into();
+
+        assert_eq!(pick_weighted(&mut board.rng, &[]), None);
+        let table = [(0, u32::MAX), (1, u32::MAX), (2, 0)];
+        let picks = (0..100)
+            .map(|_| pick_weighted(&mut board.rng, &table).unwrap())
+            .collect::<Vec<_>>();
+        assert!(picks.contains(&1));
+        assert!(!picks.contains(&2));
+    }
+}
@@ -0,0 +1,250 @@
+use bevy::{math::UVec2, platform::collections::HashSet};
+
+use crate::{board::*, mat::*};
+
+/// The special gems created by matches of more than 3 gems, each activating an effect when popped
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum Special {
+    /// Clears its whole row when popped. Created from a horizontal match of 4
+    RowClearer,
+    /// Clears its whole column when popped. Created from a vertical match of 4
+    ColumnClearer,
+    /// Clears the 3x3 area around it when popped. Created from L and T shaped matches
+    Bomb,
+    /// Clears every gem of its type when popped. Created from a match of 5 or more.
+    ///
+    /// Color bombs never match, but swapping one with any gem always succeeds and clears every gem of that gem's type
+    ColorBomb,
+}
+
+impl Board {
+    /// Returns the special gems created by popping the given positions, based on the matches currently on the board.
+    ///
+    /// A special is only created when every gem of its match is popped. It is placed on the swapped gem if that gem is
+    /// part of the match, otherwise on the intersection of an L or T shape, otherwise in the middle of the match.
+    pub(crate) fn created_specials(&self, popped: &HashSet<UVec2>) -> Vec<(UVec2, Special)> {
+        let mut created = vec![];
+        for mat in self.get_matches().iter() {
+            let gems = mat.gems();
+            if !gems.iter().all(|pos| popped.contains(pos)) {
+                continue;
+            }
+
+            let special = match mat {
+                _ if mat.longest_line() >= 5 => Special::ColorBomb,
+                Match::LShape(..) | Match::TShape(..) | Match::Cross(..) => Special::Bomb,
+                Match::Straight(gems) if gems.len() == 4 => {
+                    if is_horizontal(gems) {
+                        Special::RowClearer
+                    } else {
+                        Special::ColumnClearer
+                    }
+                }
+                Match::Straight(_) | Match::Square(_) => continue,
+            };
+
+            // Existing specials are activated rather than replaced
+            let mut candidates = gems
+                .iter()
+                .copied()
+                .filter(|pos| self.gem(pos).is_some_and(|gem| gem.special().is_none()))
+                .filter(|pos| !self.is_locked(pos))
+                .collect::<Vec<_>>();
+            candidates.sort_by_key(|pos| (pos.y, pos.x));
+            let swapped = self.last_swap.and_then(|(pos1, pos2)| {
+                [pos1, pos2]
+                    .into_iter()
+                    .find(|pos| candidates.contains(pos))
+            });
+            let pivot = mat.pivot().filter(|pivot| candidates.contains(pivot));
+            let Some(pos) = swapped
+                .or(pivot)
+                .or_else(|| candidates.get(candidates.len() / 2).copied())
+            else {
+                continue;
+            };
+            created.push((pos, special));
+        }
+        created
+    }
+
+    /// Adds every gem cleared by the specials among the popped positions to the popped positions, including
+    /// the ones cleared by specials set off by other specials. Positions in `keep` are never activated.
+    ///
+    /// Returns the activated specials in the order they went off
+    pub(crate) fn activate_specials(
+        &self,
+        popped: &mut Vec<UVec2>,
+        keep: &HashSet<UVec2>,
+    ) -> Vec<(UVec2, Special)> {
+        let mut activated = vec![];
+        let mut seen = popped.iter().copied().collect::<HashSet<_>>();
+        let mut i = 0;
+        while i < popped.len() {
+            let pos = popped[i];
+            i += 1;
+            if keep.contains(&pos) || activated.iter().any(|(other, _)| *other == pos) {
+                continue;
+            }
+            let Some((gem, special)) = self
+                .gem(&pos)
+                .and_then(|gem| gem.special().map(|special| (*gem, special)))
+            else {
+                continue;
+            };
+            activated.push((pos, special));
+            for cleared in self.special_area(&pos, &gem, special) {
+                if seen.insert(cleared) {
+                    popped.push(cleared);
+                }
+            }
+        }
+        activated
+    }
+
+    /// Returns the positions of all gems cleared by the given special at the given position, including
+    /// the obstacles taking up cells in its area
+    fn special_area(&self, pos: &UVec2, gem: &Gem, special: Special) -> Vec<UVec2> {
+        let mut area = match special {
+            Special::RowClearer => (0..self.dimensions.x)
+                .map(|x| UVec2::new(x, pos.y))
+                .collect(),
+            Special::ColumnClearer => (0..self.dimensions.y)
+                .map(|y| UVec2::new(pos.x, y))
+                .collect(),
+            Special::Bomb => (pos.x.saturating_sub(1)..=pos.x + 1)
+                .flat_map(|x| (pos.y.saturating_sub(1)..=pos.y + 1).map(move |y| UVec2::new(x, y)))
+                .collect(),
+            Special::ColorBomb => {
+                let mut area = self
+                    .gems
+                    .iter()
+                    .filter(|(_, other)| other.typ == gem.typ && other.is_matchable())
+                    .map(|(pos, _)| *pos)
+                    .collect::<Vec<_>>();
+                area.sort_by_key(|pos| (pos.y, pos.x));
+                area
+            }
+        };
+        area.retain(|cleared| {
+            cleared != pos && (self.gem(cleared).is_some() || self.is_blocked(cleared))
+        });
+        area
+    }
+
+    /// If one of the swapped gems is a color bomb, makes it take the type of the gem it was swapped with and
+    /// returns its position
+    pub(crate) fn prime_color_bomb(&mut self, pos1: &UVec2, pos2: &UVec2) -> Option<UVec2> {
+        let (bomb, other) = [(pos1, pos2), (pos2, pos1)].into_iter().find(|(bomb, _)| {
+            self.gem(bomb)
+                .is_some_and(|gem| gem.special() == Some(Special::ColorBomb))
+        })?;
+        let typ = self.gem(other)?.typ;
+        self.gems.get_mut(bomb)?.typ = typ;
+        Some(*bomb)
+    }
+
+    /// Turns the gem at the given position into the given special, keeping its type
+    pub(crate) fn make_special(&mut self, pos: &UVec2, special: Special) {
+        if let Some(gem) = self.gems.get_mut(pos) {
+            gem.kind = GemKind::Special(special);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{math::UVec2, platform::collections::HashSet};
+
+    use crate::{board::*, special::Special};
+
+    fn all(board: &Board) -> HashSet<UVec2> {
+        board.iter().map(|(pos, _)| *pos).collect()
+    }
+
+    #[test]
+    fn create_line_clearers() {
+        #[rustfmt::skip]
+        let board: Board = vec![
+            vec![ 0,  1,  2,  3,  4],
+            vec![ 5,  6,  7,  8,  9],
+            vec![10, 10, 10, 10, 14],
+            vec![15, 16, 17, 18, 19],
+            vec![20, 21, 22, 23, 19],
+            vec![25, 26, 27, 28, 19],
+            vec![30, 31, 32, 33, 19],
+        ].into();
+
+        let created = board.created_specials(&all(&board));
+
+        assert_eq!(created.len(), 2);
+        assert!(created.contains(&([2, 2].into(), Special::RowClearer)));
+        assert!(created.contains(&([4, 5].into(), Special::ColumnClearer)));
+    }
+
+    #[test]
+    fn create_bomb_and_color_bomb() {
+        #[rustfmt::skip]
+        let board: Board = vec![
+            vec![ 0,  1,  2,  3,  4],
+            vec![ 5,  6,  7,  8,  9],
+            vec![ 5,  5,  5, 13, 14],
+            vec![ 5, 16, 17, 18, 19],
+            vec![20, 21, 22, 23, 24],
+            vec![25, 26, 27, 28, 29],
+            vec![30, 30, 30, 30, 30],
+        ].into();
+
+        let created = board.created_specials(&all(&board));
+
+        assert_eq!(created.len(), 2);
+        assert!(created.contains(&([0, 2].into(), Special::Bomb)));
+        assert!(created.contains(&([2, 6].into(), Special::ColorBomb)));
+    }
+
+    #[test]
+    fn no_special_unless_fully_popped() {
+        #[rustfmt::skip]
+        let board: Board = vec![
+            vec![ 0,  1,  2,  3,  4],
+            vec![ 5,  6,  7,  8,  9],
+            vec![10, 10, 10, 10, 14],
+            vec![15, 16, 17, 18, 19],
+        ].into();
+
+        let popped = [[0, 2], [1, 2], [2, 2]]
+            .map(UVec2::from)
+            .into_iter()
+            .collect();
+
+        assert!(board.created_specials(&popped).is_empty());
+    }
+
+    #[test]
+    fn chained_specials() {
+        #[rustfmt::skip]
+        let mut board: Board = vec![
+            vec![ 0,  1,  2,  3,  4],
+            vec![ 5,  6,  7,  8,  9],
+            vec![10, 11, 12, 13, 14],
+            vec![15, 16, 17, 18, 19],
+        ].into();
+        board.make_special(&[0, 1].into(), Special::RowClearer);
+        board.make_special(&[3, 1].into(), Special::ColumnClearer);
+
+        let mut popped = vec![[0, 1].into()];
+        let activated = board.activate_specials(&mut popped, &HashSet::default());
+
+        assert_eq!(
+            activated,
+            vec![
+                ([0, 1].into(), Special::RowClearer),
+                ([3, 1].into(), Special::ColumnClearer)
+            ]
+        );
+        assert_eq!(popped.len(), 8);
+        assert!(popped.contains(&[4, 1].into()));
+        assert!(popped.contains(&[3, 0].into()));
+        assert!(popped.contains(&[3, 3].into()));
+    }
+}
@@ -1,92 +1,304 @@
-use crate::{board::*, mat::Matches};
-use bevy::prelude::*;
+use crate::{
+    board::*,
+    deadlock::*,
+    level::Level,
+    mat::Matches,
+    objective::{Progress, Tally},
+    obstacle::*,
+    score::*,
+    special::*,
+};
+use bevy::{platform::collections::HashSet, prelude::*};
 use queues::{IsQueue, Queue};
-use rand::{prelude::SliceRandom, rng};
 use std::fmt;
 
 pub(crate) fn read_commands(
-    mut commands: ResMut<BoardCommands>,
-    mut events: ResMut<BoardEvents>,
-    mut board: ResMut<Board>,
+    mut boards: Query<(&mut Board, &mut Level, &mut BoardCommands, &mut BoardEvents)>,
 ) {
-    if commands.is_changed() {
-        while let Ok(command) = commands.pop() {
-            match command {
-                BoardCommand::Swap(pos1, pos2) => {
-                    if board.swap(&pos1, &pos2).is_ok() {
-                        events
-                            .push(BoardEvent::Swapped(pos1, pos2))
-                            .map_err(|err| println!("{err}"))
-                            .unwrap();
-                        let matches = board.get_matches();
-                        events
-                            .push(BoardEvent::Matched(matches))
-                            .map_err(|err| println!("{err}"))
-                            .unwrap();
-                    } else {
-                        events
-                            .push(BoardEvent::FailedSwap(pos1, pos2))
-                            .map_err(|err| println!("{err}"))
-                            .unwrap();
-                    };
-                }
-                BoardCommand::Pop(gems) => {
-                    gems.iter().for_each(|gem| {
-                        board.remove(gem);
-                        events
-                            .push(BoardEvent::Popped(*gem))
-                            .map_err(|err| println!("{err}"))
-                            .unwrap()
-                    });
-                    let mut drops: Vec<Drop> =
-                        board.drop().iter().copied().map(|e| e.into()).collect();
-                    drops.sort();
-                    events
-                        .push(BoardEvent::Dropped(drops))
-                        .map_err(|err| println!("{err}"))
-                        .unwrap();
+    for (mut board, mut level, mut commands, mut events) in boards.iter_mut() {
+        if commands.is_changed() {
+            while let Ok(command) = commands.pop() {
+                handle_command(&mut board, &mut level, command, &mut events);
+            }
+        }
+    }
+}
 
-                    events
-                        .push(BoardEvent::Spawned(board.fill().iter().copied().collect()))
-                        .map_err(|err| println!("{err}"))
-                        .unwrap();
+/// Counts down the time of levels with a time limit, ending them right away if the board has settled
+pub(crate) fn tick_levels(
+    time: Res<Time>,
+    mut boards: Query<(&Board, &mut Level, &mut BoardEvents)>,
+) {
+    for (board, mut level, mut events) in boards.iter_mut() {
+        if level.tick(time.delta()) && board.get_matches().is_empty() {
+            if let Some(event) = level.check(board) {
+                events.push(event).map_err(|err| println!("{err}")).unwrap();
+            }
+        }
+    }
+}
 
-                    check_for_matches(&board, &mut events);
-                }
-                BoardCommand::Shuffle => {
-                    let gems = board.gems.clone();
-                    let mut values = gems.iter().collect::<Vec<_>>();
-                    let mut moves =
-                        Vec::with_capacity((board.dimensions.x * board.dimensions.y) as usize);
-                    values.shuffle(&mut rng());
-                    for ((old_key, value), new_key) in values.iter().copied().zip(gems.keys()) {
-                        board.insert(*new_key, *value);
-                        moves.push((*old_key, *new_key));
-                    }
+pub(crate) fn handle_command(
+    board: &mut Board,
+    level: &mut Level,
+    command: BoardCommand,
+    events: &mut BoardEvents,
+) {
+    match command {
+        BoardCommand::Swap(pos1, pos2) => {
+            let swapped = if level.accepts_swaps() {
+                board.swap(&pos1, &pos2)
+            } else {
+                Err(SwapError::LevelOver)
+            };
+            if let Err(err) = swapped {
+                events
+                    .push(BoardEvent::FailedSwap(pos1, pos2, err))
+                    .map_err(|err| println!("{err}"))
+                    .unwrap();
+            } else {
+                level.start_turn();
+                events
+                    .push(BoardEvent::Swapped(pos1, pos2))
+                    .map_err(|err| println!("{err}"))
+                    .unwrap();
+                if let Some(bomb) = board.prime_color_bomb(&pos1, &pos2) {
+                    pop(board, level, vec![bomb], events);
+                } else {
+                    let matches = board.get_matches();
                     events
-                        .push(BoardEvent::Shuffled(moves))
+                        .push(BoardEvent::Matched(matches))
                         .map_err(|err| println!("{err}"))
                         .unwrap();
-
-                    check_for_matches(&board, &mut events);
                 }
+            };
+        }
+        BoardCommand::Pop(gems) => pop(board, level, gems, events),
+        BoardCommand::Shuffle => {
+            level.cascade = 0;
+            let moves = board.shuffle();
+            events
+                .push(BoardEvent::Shuffled(moves))
+                .map_err(|err| println!("{err}"))
+                .unwrap();
+
+            check_for_matches(board, level, events);
+        }
+        BoardCommand::Reseed(seed) => board.reseed(seed),
+        BoardCommand::SetGemWeights(weights) => match validate_gem_weights(&weights) {
+            Ok(()) => board.weights = weights,
+            Err(err) => events
+                .push(BoardEvent::FailedGemWeights(err))
+                .map_err(|err| println!("{err}"))
+                .unwrap(),
+        },
+    }
+}
+
+fn pop(board: &mut Board, level: &mut Level, mut gems: Vec<UVec2>, events: &mut BoardEvents) {
+    let progress = level.progress(board);
+    let targets = gems.iter().copied().collect();
+    let created = board.created_specials(&targets);
+    let matched = board.scored_matches(&targets);
+    let keep = created.iter().map(|(pos, _)| *pos).collect::<HashSet<_>>();
+    let activated = board.activate_specials(&mut gems, &keep);
+    activated.iter().for_each(|(pos, special)| {
+        events
+            .push(BoardEvent::SpecialActivated(*pos, *special))
+            .map_err(|err| println!("{err}"))
+            .unwrap()
+    });
+    let mut hits = vec![];
+    let mut popped = vec![];
+    let mut tally = Tally::default();
+    gems.iter()
+        .filter(|gem| !keep.contains(*gem))
+        .for_each(|gem| {
+            // Ingredients survive everything, they only leave the board through exit cells
+            if board
+                .gem(gem)
+                .is_some_and(|gem| gem.kind == GemKind::Ingredient)
+            {
+                return;
+            }
+            // Chains and obstacles taking up the cell take the hit in place of a gem
+            if board.is_locked(gem) || board.is_blocked(gem) {
+                hits.push(*gem);
+                return;
+            }
+            if board.obstacle(gem).is_some() {
+                hits.push(*gem);
+            }
+            tally.popped.extend(board.get(gem));
+            board.remove(gem);
+            popped.push(*gem);
+            events
+                .push(BoardEvent::Popped(*gem))
+                .map_err(|err| println!("{err}"))
+                .unwrap()
+        });
+    // Gems turned into specials were matched as well, so the ice under them cracks
+    let mut kept = keep.iter().copied().collect::<Vec<_>>();
+    kept.sort_by_key(|pos| (pos.y, pos.x));
+    tally
+        .popped
+        .extend(kept.iter().filter_map(|pos| board.get(pos)));
+    hits.extend(kept.iter().filter(|pos| board.obstacle(pos).is_some()));
+    // Obstacles taking up a cell also take a single hit from any number of gems popped next to them
+    let mut adjacent = popped
+        .iter()
+        .chain(kept.iter())
+        .flat_map(|pos| [pos.left(), pos.right(), pos.up(), pos.down()])
+        .filter(|pos| board.is_blocked(pos) && !hits.contains(pos))
+        .collect::<Vec<_>>();
+    adjacent.sort_by_key(|pos| (pos.y, pos.x));
+    adjacent.dedup();
+    hits.append(&mut adjacent);
+    hits.into_iter().for_each(|pos| {
+        if let Some(event) = board.hit_obstacle(&pos) {
+            if let BoardEvent::ObstacleDestroyed(_, kind) = event {
+                level.hazard_destroyed |= kind == ObstacleKind::Hazard;
+                tally.destroyed.push(kind);
             }
+            events.push(event).map_err(|err| println!("{err}")).unwrap()
+        }
+    });
+    // Jelly loses a layer under every popped gem, including the gems turned into specials
+    let mut cleared = popped.clone();
+    cleared.extend(kept.iter());
+    board
+        .hit_jelly(&cleared)
+        .into_iter()
+        .for_each(|(pos, layers)| {
+            if layers == 0 {
+                tally.jelly += 1;
+            }
+            events
+                .push(BoardEvent::JellyChanged(pos, layers))
+                .map_err(|err| println!("{err}"))
+                .unwrap()
+        });
+    created.into_iter().for_each(|(pos, special)| {
+        board.make_special(&pos, special);
+        events
+            .push(BoardEvent::SpecialCreated(pos, special))
+            .map_err(|err| println!("{err}"))
+            .unwrap()
+    });
+    level
+        .score_pop(board.scoring(), matched, &activated)
+        .into_iter()
+        .for_each(|score| {
+            events
+                .push(BoardEvent::Scored(score))
+                .map_err(|err| println!("{err}"))
+                .unwrap()
+        });
+    board.last_swap = None;
+
+    // Gems spawned in spawner cells fall into the board before the next ones can spawn
+    let mut emptied = true;
+    loop {
+        let drops: Vec<Drop> = board.drop().into_iter().map(|e| e.into()).collect();
+        if !emptied && drops.is_empty() {
+            break;
+        }
+        emptied = false;
+        events
+            .push(BoardEvent::Dropped(drops))
+            .map_err(|err| println!("{err}"))
+            .unwrap();
+
+        // Ingredients that reached an exit are collected before anything spawns, so the gems above fall in first
+        let collected = board.collect_ingredients();
+        if !collected.is_empty() {
+            tally.ingredients += collected.len() as u64;
+            collected.into_iter().for_each(|(pos, typ)| {
+                events
+                    .push(BoardEvent::Collected(pos, typ))
+                    .map_err(|err| println!("{err}"))
+                    .unwrap()
+            });
+            emptied = true;
+            continue;
+        }
+
+        let mut spawns = board.fill().iter().copied().collect::<Vec<_>>();
+        spawns.sort_by_key(|(pos, _)| (pos.y, pos.x));
+        let done = spawns.is_empty() || board.spawners.is_empty();
+        events
+            .push(BoardEvent::Spawned(spawns))
+            .map_err(|err| println!("{err}"))
+            .unwrap();
+        if done {
+            break;
         }
     }
+
+    level
+        .track_objectives(board, &progress, &tally)
+        .into_iter()
+        .for_each(|event| events.push(event).map_err(|err| println!("{err}")).unwrap());
+
+    check_for_matches(board, level, events);
 }
 
-fn check_for_matches(board: &ResMut<Board>, events: &mut ResMut<BoardEvents>) {
+/// Reports the matches left on the board once it has settled. If there are none the turn ends, the board's level
+/// may end, and the deadlock is resolved if there are no matching moves either
+fn check_for_matches(board: &mut Board, level: &mut Level, events: &mut BoardEvents) {
     let matches = board.get_matches();
     if !matches.is_empty() {
         events
             .push(BoardEvent::Matched(matches))
             .map_err(|err| println!("{err}"))
             .unwrap();
+    } else {
+        // The turn ends once the board has settled, before the level is judged, and the next pop starts a new cascade
+        level.cascade = 0;
+        let progress = level.progress(board);
+        if let Some(event) = board.end_turn(level) {
+            events.push(event).map_err(|err| println!("{err}")).unwrap();
+            // A spreading hazard can set the objectives back
+            level
+                .track_objectives(board, &progress, &Tally::default())
+                .into_iter()
+                .for_each(|event| events.push(event).map_err(|err| println!("{err}")).unwrap());
+        }
+        if let Some(event) = level.check(board) {
+            events.push(event).map_err(|err| println!("{err}")).unwrap();
+        }
+        if board.get_matching_moves().is_empty() {
+            resolve_deadlock(board, events);
+        }
     }
 }
 
-/// The resource used to send commands to the logic board
-#[derive(Default, Resource)]
+fn resolve_deadlock(board: &mut Board, events: &mut BoardEvents) {
+    events
+        .push(BoardEvent::NoMovesLeft)
+        .map_err(|err| println!("{err}"))
+        .unwrap();
+    if board.deadlock_policy == DeadlockPolicy::Shuffle {
+        if let Some(moves) = board.playable_shuffle() {
+            events
+                .push(BoardEvent::Shuffled(moves))
+                .map_err(|err| println!("{err}"))
+                .unwrap();
+            return;
+        }
+    }
+    if board.deadlock_policy != DeadlockPolicy::Nothing {
+        if let Some(gems) = board.regenerate() {
+            events
+                .push(BoardEvent::Regenerated(gems))
+                .map_err(|err| println!("{err}"))
+                .unwrap();
+        }
+    }
+}
+
+/// The component used to send commands to the logic board on the same entity
+#[derive(Default, Component)]
 pub struct BoardCommands(pub(crate) Queue<BoardCommand>);
 
 impl BoardCommands {
@@ -100,8 +312,11 @@ impl BoardCommands {
     /// use bevy_match3::prelude::*;
     ///
     /// fn example_system(
-    ///     mut board_commands: ResMut<BoardCommands>,
+    ///     mut boards: Query<&mut BoardCommands>,
     /// ) {
+    ///     let Ok(mut board_commands) = boards.single_mut() else {
+    ///         return;
+    ///     };
     ///     board_commands.push(BoardCommand::Swap(
     ///         [0, 0].into(),
     ///         [0, 1].into(),
@@ -121,12 +336,26 @@ impl BoardCommands {
 /// The commands that can be issued to the logic board
 #[derive(Clone)]
 pub enum BoardCommand {
-    /// Attempts to swap two gems, succeeds only if the swap would cause a match
+    /// Attempts to swap two gems, succeeds only if the swap would cause a match or one of the gems is a color bomb.
+    /// Chained gems can never be swapped, and no swaps succeed once the board's level has run out of moves or time
     Swap(UVec2, UVec2),
-    /// Pops all gems at the given positions, causing drops, spawns, and may cause matches to occur
+    /// Pops all gems at the given positions, causing drops, spawns, and may cause matches to occur.
+    ///
+    /// Popping a complete match of more than 3 gems leaves a special gem behind, and popping a special gem
+    /// activates it, popping every gem in its area as well.
+    ///
+    /// Obstacles take a hit when the gem on or under them is popped, chained gems stay in place while their chain
+    /// takes the hit, and crates and stones take a hit from gems popped next to them
     Pop(Vec<UVec2>),
-    /// Shuffles all gems on the board, may result in matches
+    /// Shuffles all gems on the board following the board's ``ShuffleMode``. The default
+    /// ``ShuffleMode::Playable`` never results in matches and always leaves at least one matching move if possible,
+    /// while ``ShuffleMode::Random`` may result in matches
     Shuffle,
+    /// Re-seeds the board's random number generator, making all following spawns and shuffles reproducible
+    Reseed(u64),
+    /// Replaces the gem types new gems are picked from together with their weights, see ``Board::with_gem_weights``.
+    /// Invalid tables are rejected with a ``BoardEvent::FailedGemWeights`` event
+    SetGemWeights(Vec<(u32, u32)>),
 }
 
 impl BoardEvents {
@@ -140,8 +369,8 @@ impl BoardEvents {
     }
 }
 
-/// The resource used to receive information about changes in the logic board
-#[derive(Default, Resource)]
+/// The component used to receive information about changes in the logic board on the same entity
+#[derive(Default, Component)]
 pub struct BoardEvents(pub(crate) Queue<BoardEvent>);
 
 /// The events that indicate a possible change in the logic board
@@ -149,26 +378,66 @@ pub struct BoardEvents(pub(crate) Queue<BoardEvent>);
 pub enum BoardEvent {
     /// Two gems have been successfully swapped, usually as a result of a ``BoardCommand::Swap`` command
     Swapped(UVec2, UVec2),
-    /// Two gems have failed to swap, this means no changes have been made to the logic board.
+    /// Two gems have failed to swap for the given reason, this means no changes have been made to the logic board.
     ///
     /// This is usually as a result of a ``BoardCommand::Swap`` command
-    FailedSwap(UVec2, UVec2),
-    /// One or more gems have dropped from a higher position to a lower position, or in other words their
-    /// position has changed from a lower y-value to a higher y-value with no change in x-value.
-    /// These are ordered so that for each column the drop with the highest y-value on its from coordinate
-    /// comes first, since we want to avoid overwriting other gems.
+    FailedSwap(UVec2, UVec2, SwapError),
+    /// One or more gems have fallen along the gravity of the cells they passed through, which can move them along
+    /// any axis, and may have slid in diagonally if the board allows it. These are ordered so that a gem only moves
+    /// into a cell once the gem that used to be there has moved on, since we want to avoid overwriting other gems.
     Dropped(Vec<Drop>),
     /// A gem has been popped. This is usually as a result of a ``BoardCommand::Pop`` command
     Popped(UVec2),
-    /// Gems have been spawned. This usually happens after a ``BoardEvent::Popped`` event
+    /// Gems have been spawned. This usually happens after a ``BoardEvent::Popped`` event. Spawners may spawn
+    /// ingredients, which can be told apart with ``Board::gem``.
+    ///
+    /// On boards with spawners, gems only spawn in the spawner cells and every spawn is followed by a
+    /// ``BoardEvent::Dropped`` event letting them fall into the board
     Spawned(Vec<(UVec2, u32)>),
     /// Matches have been detected.
     Matched(Matches),
-    /// The board has been shuffled, this is is the list of moves from .0 to .1
+    /// The board has been shuffled, this is is the list of moves from .0 to .1.
+    ///
+    /// Boards with ``DeadlockPolicy::Shuffle`` shuffle themselves when they settle with no matching moves left
     Shuffled(Vec<(UVec2, UVec2)>),
+    /// The board has settled with no matches and no matching moves left. What follows depends on the board's
+    /// ``DeadlockPolicy``, with ``DeadlockPolicy::Nothing`` the board stays stuck until it is sent a command
+    NoMovesLeft,
+    /// Gems have been given new types in place because the board settled with no matching moves left and has
+    /// ``DeadlockPolicy::Regenerate``, or could not be shuffled into a playable board. This is the list of the
+    /// changed gems with their new types
+    Regenerated(Vec<(UVec2, u32)>),
+    /// A special gem has been created from a match. The gem at the position was not popped, but turned into the special
+    SpecialCreated(UVec2, Special),
+    /// A special gem has been activated, the gems it clears follow as ``BoardEvent::Popped`` events
+    SpecialActivated(UVec2, Special),
+    /// An obstacle has lost a hit point, this is the obstacle with the hit points it has left
+    ObstacleDamaged(UVec2, Obstacle),
+    /// An obstacle has lost its last hit point and has been removed from the board
+    ObstacleDestroyed(UVec2, ObstacleKind),
+    /// The level played on the board has ended with every objective met, see ``Level``
+    LevelWon,
+    /// The level played on the board has run out of moves or time before every objective was met
+    LevelLost,
+    /// A hazard (.0) has spread into the cell next to it (.1) at the end of a turn in which no hazard was destroyed,
+    /// replacing the gem there
+    HazardSpread(UVec2, UVec2),
+    /// The jelly at the given position has lost a layer or spread to it, this is the number of layers it has
+    /// now. Jelly with 0 layers is gone
+    JellyChanged(UVec2, u32),
+    /// An ingredient of the given type has reached an exit cell and has been removed from the board
+    Collected(UVec2, u32),
+    /// An objective of the level played on the board has made progress, this is the index of the objective in
+    /// ``Level::objectives`` with its new progress
+    ObjectiveProgress(usize, Progress),
+    /// Points have been awarded for a popped match or an activated special, only sent by boards with scoring.
+    /// Every ``BoardCommand::Pop`` since the last swap is a deeper round of the cascade with a higher multiplier
+    Scored(Score),
+    /// A ``BoardCommand::SetGemWeights`` command has been rejected, so new gems are still picked as before
+    FailedGemWeights(GemWeightsError),
 }
 
-/// Represents a gem dropping from a higher to a lower position
+/// Represents a gem falling from one position to another along the board's gravity
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub struct Drop {
     /// The position the gem used to occupy
@@ -205,7 +474,7 @@ impl fmt::Debug for Drop {
 mod tests {
     #[test]
     fn sort_drops() {
-        let mut drops: Vec<crate::Drop> = vec![
+        let mut drops: Vec<super::Drop> = vec![
             ((0, 1).into(), (0, 2).into()).into(),
             ((1, 0).into(), (1, 2).into()).into(),
             ((4, 1).into(), (4, 2).into()).into(),
@@ -224,7 +493,7 @@ mod tests {
             ((3, 1).into(), (3, 2).into()).into(),
         ];
 
-        let sorted_correctly_drops: Vec<crate::Drop> = vec![
+        let sorted_correctly_drops: Vec<super::Drop> = vec![
             ((0, 3).into(), (0, 4).into()).into(),
             ((0, 2).into(), (0, 3).into()).into(),
             ((2, 2).into(), (2, 4).into()).into(),
@@ -0,0 +1,46 @@
+use crate::{board::*, level::Level, systems::*};
+
+/// A board where swapping (2, 3) with (3, 3) completes a column of 7s, and popping it drops a 0 into the row of
+/// 0s below it for a second round of matches
+pub(crate) fn cascade_board() -> Board {
+    #[rustfmt::skip]
+    let board: Board = vec![
+        vec![ 1,  2,  0,  3,  4],
+        vec![ 5,  6,  7,  8,  9],
+        vec![10, 11,  7, 13, 14],
+        vec![ 0,  0, 15,  7, 19],
+        vec![20, 21, 22, 23, 24],
+    ].into();
+    board
+}
+
+/// Handles the given command, returning every event it sent without popping any matches
+pub(crate) fn events(
+    board: &mut Board,
+    level: &mut Level,
+    command: BoardCommand,
+) -> Vec<BoardEvent> {
+    let mut events = BoardEvents::default();
+    handle_command(board, level, command, &mut events);
+    let mut all = vec![];
+    while let Ok(event) = events.pop() {
+        all.push(event);
+    }
+    all
+}
+
+/// Handles the given command and pops every match as soon as it is reported, like a consumer of the board would,
+/// returning every event sent until the board has settled
+pub(crate) fn play(board: &mut Board, level: &mut Level, command: BoardCommand) -> Vec<BoardEvent> {
+    let mut events = BoardEvents::default();
+    handle_command(board, level, command, &mut events);
+    let mut all = vec![];
+    while let Ok(event) = events.pop() {
+        if let BoardEvent::Matched(matches) = &event {
+            let gems = matches.without_duplicates().into_iter().collect();
+            handle_command(board, level, BoardCommand::Pop(gems), &mut events);
+        }
+        all.push(event);
+    }
+    all
+}